@@ -0,0 +1,83 @@
+#![no_main]
+
+use std::collections::HashMap;
+
+use arbitrary::Arbitrary;
+use brickadia::save::{
+    Brick, BrickColor, BrickOwner, Collision, Color, Direction, Rotation, SaveData, Size,
+};
+use brs2rbxl::{convert_brick, ConvertOptions};
+use libfuzzer_sys::fuzz_target;
+
+/// The brickadia save types don't implement `Arbitrary`, so the fuzzer fills in this instead and
+/// it's turned into a save with a single brick. Indices are left unclamped on purpose, to exercise
+/// the out of range paths.
+#[derive(Arbitrary, Debug)]
+struct Input {
+    brick_assets: Vec<String>,
+    colors: Vec<(u8, u8, u8, u8)>,
+    materials: Vec<String>,
+    brick_owners: u8,
+    asset_name_index: u32,
+    size: Option<(u32, u32, u32)>,
+    position: (i32, i32, i32),
+    direction: u8,
+    rotation: u8,
+    player_collision: bool,
+    visibility: bool,
+    material_index: u32,
+    material_intensity: u32,
+    color: Result<u32, (u8, u8, u8, u8)>,
+    owner_index: u32,
+    scale: f32,
+}
+
+fuzz_target!(|input: Input| {
+    let mut save = SaveData::default();
+    save.header2.brick_assets = input.brick_assets;
+    save.header2.colors = input
+        .colors
+        .into_iter()
+        .map(|(r, g, b, a)| Color { r, g, b, a })
+        .collect();
+    save.header2.materials = input.materials;
+    save.header2.brick_owners = (0..input.brick_owners)
+        .map(|i| BrickOwner {
+            name: format!("owner {i}"),
+            id: Default::default(),
+            bricks: 0,
+        })
+        .collect();
+
+    let brick = Brick {
+        asset_name_index: input.asset_name_index,
+        size: match input.size {
+            Some((x, y, z)) => Size::Procedural(x, y, z),
+            None => Size::Empty,
+        },
+        position: input.position,
+        direction: Direction::try_from(input.direction % 6).unwrap(),
+        rotation: Rotation::try_from(input.rotation % 4).unwrap(),
+        collision: Collision {
+            player: input.player_collision,
+            ..Collision::for_all(true)
+        },
+        visibility: input.visibility,
+        material_index: input.material_index,
+        material_intensity: input.material_intensity,
+        color: match input.color {
+            Ok(index) => BrickColor::Index(index),
+            Err((r, g, b, a)) => BrickColor::Unique(Color { r, g, b, a }),
+        },
+        owner_index: input.owner_index,
+        components: HashMap::new(),
+        ..Default::default()
+    };
+
+    // the cli only accepts finite, positive scales
+    let mut options = ConvertOptions::default();
+    if input.scale.is_finite() && input.scale > 0.0 {
+        options.scale = input.scale;
+    }
+    let _ = convert_brick(&brick, &save, &options);
+});
@@ -3,6 +3,7 @@ use std::ops::Mul;
 use rbx_dom_weak::types::{CFrame, Matrix3, Vector3};
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CoordinateFrame {
     matrix: [[f32; 4]; 4],
 }
@@ -27,6 +28,10 @@ impl From<CoordinateFrame> for CFrame {
 }
 
 impl CoordinateFrame {
+    pub fn identity() -> Self {
+        Self::default()
+    }
+
     pub fn new(x: f32, y: f32, z: f32) -> Self {
         Self {
             matrix: [
@@ -38,6 +43,7 @@ impl CoordinateFrame {
         }
     }
 
+    /// A translation with a rotation given as 9 row-major values, like `ORIENTATION_MAP`'s.
     pub fn from_rotation(x: f32, y: f32, z: f32, rot: [f32; 9]) -> Self {
         Self {
             matrix: [
@@ -49,10 +55,140 @@ impl CoordinateFrame {
         }
     }
 
+    /// Builds a frame from a 4x4 matrix given as rows, so `mat[row][column]`. The translation is
+    /// the last column.
+    pub fn from_matrix_row_major(mat: [[f32; 4]; 4]) -> Self {
+        Self { matrix: mat }
+    }
+
+    /// Builds a frame from a 4x4 matrix given as columns, so `mat[column][row]`. The translation is
+    /// `mat[3]`.
+    pub fn from_matrix_col_major(mat: [[f32; 4]; 4]) -> Self {
+        let mut matrix = [[0.0; 4]; 4];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = mat[j][i];
+            }
+        }
+        Self { matrix }
+    }
+
+    /// Builds a frame from 16 row-major values of a 4x4 matrix.
+    pub fn from_array_16(m: [f32; 16]) -> Self {
+        Self {
+            matrix: [
+                [m[0], m[1], m[2], m[3]],
+                [m[4], m[5], m[6], m[7]],
+                [m[8], m[9], m[10], m[11]],
+                [m[12], m[13], m[14], m[15]],
+            ],
+        }
+    }
+
     pub fn angles(x: f32, y: f32, z: f32) -> Self {
         Self::rz(z) * Self::ry(y) * Self::rx(x)
     }
 
+    /// Intrinsic XYZ rotation: rotates about X, then about the *rotated* Y, then the rotated Z,
+    /// which composes as `rx * ry * rz`.
+    ///
+    /// This is not the same as [`CoordinateFrame::angles`], which is extrinsic ZYX (`rz * ry * rx`,
+    /// every rotation about the fixed world axes). Both take the same arguments, so mixing them up
+    /// produces orientations that are only subtly wrong. Brickadia's axes are remapped to Roblox
+    /// (`x, z, y`) before any rotation is applied; see `ORIENTATION_MAP` in `part.rs`.
+    pub fn from_euler_angles_intrinsic(rx: f32, ry: f32, rz: f32) -> Self {
+        Self::rx(rx) * Self::ry(ry) * Self::rz(rz)
+    }
+
+    /// Roblox's `CFrame.fromEulerAnglesXYZ` (and `CFrame.Angles`): `rx * ry * rz`. This is the
+    /// same rotation as [`CoordinateFrame::from_euler_angles_intrinsic`], under roblox's name, and
+    /// the reverse order of [`CoordinateFrame::angles`].
+    pub fn from_euler_angles_xyz(x: f32, y: f32, z: f32) -> Self {
+        Self::from_euler_angles_intrinsic(x, y, z)
+    }
+
+    /// Roblox's `CFrame.fromEulerAnglesYXZ` (and `CFrame.fromOrientation`): `ry * rx * rz`.
+    pub fn from_euler_angles_yxz(x: f32, y: f32, z: f32) -> Self {
+        Self::ry(y) * Self::rx(x) * Self::rz(z)
+    }
+
+    /// A rotation of `angle` radians about the axis `(ax, ay, az)`, which doesn't need to be
+    /// normalized, via Rodrigues' rotation formula. A zero axis has no direction to rotate about,
+    /// so gives the identity.
+    pub fn from_axis_angle(ax: f32, ay: f32, az: f32, angle: f32) -> Self {
+        let len = (ax * ax + ay * ay + az * az).sqrt();
+        if len == 0.0 {
+            return Self::identity();
+        }
+        let (x, y, z) = (ax / len, ay / len, az / len);
+        let (sin, cos) = angle.sin_cos();
+        let t = 1.0 - cos;
+
+        Self::from_rotation(
+            0.0,
+            0.0,
+            0.0,
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+            ],
+        )
+    }
+
+    /// A frame at `from` facing `to`. Like roblox's `CFrame.lookAt`, the frame looks down its -Z
+    /// axis, with +Y as close to `up` as possible. When facing straight along `up` there's no
+    /// single answer, so another world axis is used as up instead. `from` and `to` must differ.
+    pub fn look_at(from: (f32, f32, f32), to: (f32, f32, f32), up: (f32, f32, f32)) -> Self {
+        let normalize = |v: [f32; 3]| {
+            let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+            [v[0] / len, v[1] / len, v[2] / len]
+        };
+        let cross = |a: [f32; 3], b: [f32; 3]| {
+            [
+                a[1] * b[2] - a[2] * b[1],
+                a[2] * b[0] - a[0] * b[2],
+                a[0] * b[1] - a[1] * b[0],
+            ]
+        };
+
+        let forward = normalize([to.0 - from.0, to.1 - from.1, to.2 - from.2]);
+        let mut right = cross(forward, [up.0, up.1, up.2]);
+        if right.iter().all(|v| v.abs() < 1e-6) {
+            let fallback = if forward[0].abs() < 0.9 {
+                [1.0, 0.0, 0.0]
+            } else {
+                [0.0, 0.0, 1.0]
+            };
+            right = cross(forward, fallback);
+        }
+        let right = normalize(right);
+        let up = cross(right, forward);
+
+        Self::from_rotation(
+            from.0,
+            from.1,
+            from.2,
+            [
+                right[0],
+                up[0],
+                -forward[0],
+                right[1],
+                up[1],
+                -forward[1],
+                right[2],
+                up[2],
+                -forward[2],
+            ],
+        )
+    }
+
     pub fn rx(angle: f32) -> Self {
         Self {
             matrix: [
@@ -90,6 +226,198 @@ impl CoordinateFrame {
         Vector3::new(self.matrix[0][3], self.matrix[1][3], self.matrix[2][3])
     }
 
+    /// Transforms `v` by this frame. The translation is only applied when `is_point` is set;
+    /// directions are just rotated.
+    #[inline]
+    pub fn mul(&self, v: &[f32; 3], is_point: bool) -> [f32; 3] {
+        let m = &self.matrix;
+        let w = if is_point { 1.0 } else { 0.0 };
+        [
+            m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2] + m[0][3] * w,
+            m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2] + m[1][3] * w,
+            m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2] + m[2][3] * w,
+        ]
+    }
+
+    /// Whether this frame is a proper rigid transform (an orthonormal, right-handed rotation plus a
+    /// translation), i.e. something Roblox can represent as a `CFrame`.
+    pub fn is_rigid(&self) -> bool {
+        const EPSILON: f32 = 1e-4;
+        let m = &self.matrix;
+
+        let dot = |a: usize, b: usize| (0..3).map(|k| m[a][k] * m[b][k]).sum::<f32>();
+        let orthonormal = (0..3)
+            .all(|i| (0..3).all(|j| (dot(i, j) - if i == j { 1.0 } else { 0.0 }).abs() < EPSILON));
+
+        let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+
+        orthonormal && (det - 1.0).abs() < EPSILON && m[3] == [0.0, 0.0, 0.0, 1.0]
+    }
+
+    /// Whether this frame is the identity, i.e. does nothing, within float error.
+    pub fn is_identity(&self) -> bool {
+        const EPSILON: f32 = 1e-6;
+        (0..4).all(|i| {
+            (0..4).all(|j| (self.matrix[i][j] - if i == j { 1.0 } else { 0.0 }).abs() < EPSILON)
+        })
+    }
+
+    /// Scales the translation by `s`, leaving the rotation alone.
+    pub fn scale(self, s: f32) -> Self {
+        self.scale_xyz(s, s, s)
+    }
+
+    /// Scales each axis of the translation separately, leaving the rotation alone.
+    pub fn scale_xyz(mut self, sx: f32, sy: f32, sz: f32) -> Self {
+        self.matrix[0][3] *= sx;
+        self.matrix[1][3] *= sy;
+        self.matrix[2][3] *= sz;
+        self
+    }
+
+    /// Rounds the translation to the nearest multiple of `grid`, leaving the rotation alone.
+    pub fn snap_to_grid(mut self, grid: f32) -> Self {
+        for row in self.matrix.iter_mut().take(3) {
+            row[3] = (row[3] / grid).round() * grid;
+        }
+        self
+    }
+
+    /// Inverse of a rigid transform: the transposed rotation, with the translation rotated back and
+    /// negated. Only valid when [`CoordinateFrame::is_rigid`] holds.
+    pub fn inverse(&self) -> Self {
+        let m = &self.matrix;
+        let t = [m[0][3], m[1][3], m[2][3]];
+        let mut matrix = [[0.0; 4]; 4];
+
+        for i in 0..3 {
+            for j in 0..3 {
+                matrix[i][j] = m[j][i];
+            }
+            matrix[i][3] = -(m[0][i] * t[0] + m[1][i] * t[1] + m[2][i] * t[2]);
+        }
+        matrix[3][3] = 1.0;
+
+        Self { matrix }
+    }
+
+    /// Builds a frame from a unit quaternion `(x, y, z, w)` and a translation. The quaternion is
+    /// normalized first.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_quaternion(x: f32, y: f32, z: f32, w: f32, tx: f32, ty: f32, tz: f32) -> Self {
+        let len = (x * x + y * y + z * z + w * w).sqrt();
+        let (x, y, z, w) = (x / len, y / len, z / len, w / len);
+
+        Self::from_rotation(
+            tx,
+            ty,
+            tz,
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - z * w),
+                2.0 * (x * z + y * w),
+                2.0 * (x * y + z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - x * w),
+                2.0 * (x * z - y * w),
+                2.0 * (y * z + x * w),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        )
+    }
+
+    /// The rotation as a unit quaternion `(w, x, y, z)`, using Shepperd's method (branching on the
+    /// largest diagonal term so it stays stable near 180 degree rotations).
+    pub fn to_quaternion(&self) -> (f32, f32, f32, f32) {
+        let m = &self.matrix;
+        let trace = m[0][0] + m[1][1] + m[2][2];
+
+        if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            (
+                0.25 * s,
+                (m[2][1] - m[1][2]) / s,
+                (m[0][2] - m[2][0]) / s,
+                (m[1][0] - m[0][1]) / s,
+            )
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+            (
+                (m[2][1] - m[1][2]) / s,
+                0.25 * s,
+                (m[0][1] + m[1][0]) / s,
+                (m[0][2] + m[2][0]) / s,
+            )
+        } else if m[1][1] > m[2][2] {
+            let s = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+            (
+                (m[0][2] - m[2][0]) / s,
+                (m[0][1] + m[1][0]) / s,
+                0.25 * s,
+                (m[1][2] + m[2][1]) / s,
+            )
+        } else {
+            let s = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+            (
+                (m[1][0] - m[0][1]) / s,
+                (m[0][2] + m[2][0]) / s,
+                (m[1][2] + m[2][1]) / s,
+                0.25 * s,
+            )
+        }
+    }
+
+    /// Interpolates between two frames: the translation linearly, the rotation by normalized lerp
+    /// of the two quaternions (taking the shorter way around).
+    pub fn lerp(&self, other: &CoordinateFrame, t: f32) -> CoordinateFrame {
+        let (aw, ax, ay, az) = self.to_quaternion();
+        let (mut bw, mut bx, mut by, mut bz) = other.to_quaternion();
+        if aw * bw + ax * bx + ay * by + az * bz < 0.0 {
+            (bw, bx, by, bz) = (-bw, -bx, -by, -bz);
+        }
+
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        let a = self.position();
+        let b = other.position();
+
+        Self::from_quaternion(
+            mix(ax, bx),
+            mix(ay, by),
+            mix(az, bz),
+            mix(aw, bw),
+            mix(a.x, b.x),
+            mix(a.y, b.y),
+            mix(a.z, b.z),
+        )
+    }
+
+    /// Splits this frame into its translation, rotation and per-axis scale. The scale is the length
+    /// of each column of the 3x3 part, and the rotation is that part with the scale divided back
+    /// out, in rows.
+    pub fn decompose(&self) -> (Vector3, [[f32; 3]; 3], Vector3) {
+        let m = &self.matrix;
+        let scale = [0, 1, 2].map(|j| (0..3).map(|i| m[i][j] * m[i][j]).sum::<f32>().sqrt());
+
+        let mut rotation = [[0.0; 3]; 3];
+        for i in 0..3 {
+            for j in 0..3 {
+                rotation[i][j] = if scale[j] == 0.0 {
+                    0.0
+                } else {
+                    m[i][j] / scale[j]
+                };
+            }
+        }
+
+        (
+            self.position(),
+            rotation,
+            Vector3::new(scale[0], scale[1], scale[2]),
+        )
+    }
+
     pub fn rotation_matrix(&self) -> Matrix3 {
         Matrix3::new(
             Vector3::new(self.matrix[0][0], self.matrix[0][1], self.matrix[0][2]),
@@ -118,3 +446,13 @@ impl Mul for CoordinateFrame {
         Self { matrix }
     }
 }
+
+impl Mul<Vector3> for CoordinateFrame {
+    type Output = Vector3;
+
+    /// Transforms a point, so the translation applies.
+    fn mul(self, rhs: Vector3) -> Self::Output {
+        let [x, y, z] = CoordinateFrame::mul(&self, &[rhs.x, rhs.y, rhs.z], true);
+        Vector3::new(x, y, z)
+    }
+}
@@ -0,0 +1,51 @@
+use brickadia::save::{Brick, SaveData};
+
+use crate::part::{asset_name, brick_color, material_name, srgb_bytes};
+
+/// Decides which bricks in a save get converted at all. Bricks that don't match are left out
+/// entirely rather than reported as skipped.
+#[derive(Clone, Default)]
+pub struct BrickFilter {
+    /// Asset names to convert, either as glob patterns or as substrings. Empty matches every
+    /// asset.
+    pub assets: Vec<String>,
+    /// Convert the bricks that *don't* match `assets` instead.
+    pub exclude_assets: bool,
+    /// Material names to convert, e.g. `BMC_Glow`. Empty matches every material.
+    pub materials: Vec<String>,
+    /// Only convert bricks of this color, in the same 0-255 sRGB the roblox part ends up with.
+    pub color: Option<[u8; 3]>,
+    /// How far each channel may be from `color` and still match.
+    pub color_tolerance: u8,
+}
+
+impl BrickFilter {
+    pub fn matches(&self, brick: &Brick, save: &SaveData) -> bool {
+        let asset = asset_name(save, brick).unwrap_or_default();
+
+        (self.assets.is_empty()
+            || self.assets.iter().any(|p| matches_asset(p, asset)) != self.exclude_assets)
+            && (self.materials.is_empty()
+                || self
+                    .materials
+                    .iter()
+                    .any(|m| m == material_name(save, brick)))
+            && self
+                .color
+                .is_none_or(|color| self.matches_color(brick, save, color))
+    }
+
+    fn matches_color(&self, brick: &Brick, save: &SaveData, color: [u8; 3]) -> bool {
+        srgb_bytes(brick_color(save, brick))
+            .iter()
+            .zip(color)
+            .all(|(&c, target)| c.abs_diff(target) <= self.color_tolerance)
+    }
+}
+
+fn matches_asset(pattern: &str, asset: &str) -> bool {
+    match glob::Pattern::new(pattern) {
+        Ok(glob) if pattern.contains(['*', '?', '[']) => glob.matches(asset),
+        _ => asset.contains(pattern),
+    }
+}
@@ -0,0 +1,379 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use brickadia::save::{Brick, BrickColor, Color, SaveData, Size};
+use rayon::prelude::*;
+use rbx_dom_weak::{
+    types::{Attributes, Enum},
+    InstanceBuilder, WeakDom,
+};
+
+use crate::part::asset_name;
+
+pub mod cframe;
+pub mod filter;
+pub mod part;
+pub mod validate;
+
+pub use cframe::CoordinateFrame;
+pub use filter::BrickFilter;
+pub use part::{convert_brick, CollisionMode, ConvertOptions, GroupBy, PartDef, ASSETS};
+
+/// The result of converting a whole save.
+pub struct Conversion {
+    pub model: InstanceBuilder,
+    pub report: Report,
+}
+
+/// Brick counts from a conversion.
+#[derive(Default)]
+pub struct Report {
+    pub total: usize,
+    pub converted: usize,
+    pub skipped: usize,
+    /// How many of the skipped bricks were procedural bricks with no size.
+    pub empty: usize,
+    /// The combined volume of the converted procedural bricks, in cubic studs.
+    pub brick_volume: f64,
+    /// How many converted bricks aren't procedural, so aren't counted in `brick_volume`.
+    pub sizeless: usize,
+    /// How many bricks were skipped for each asset that has no converter.
+    pub unknown_assets: BTreeMap<String, usize>,
+}
+
+impl Report {
+    /// Adds the counts from another conversion to these.
+    pub fn merge(&mut self, other: Report) {
+        self.total += other.total;
+        self.converted += other.converted;
+        self.skipped += other.skipped;
+        self.empty += other.empty;
+        self.brick_volume += other.brick_volume;
+        self.sizeless += other.sizeless;
+        for (asset, count) in other.unknown_assets {
+            *self.unknown_assets.entry(asset).or_default() += count;
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "total": self.total,
+            "converted": self.converted,
+            "skipped": self.skipped,
+            "empty": self.empty,
+            "brick_volume": self.brick_volume,
+            "sizeless": self.sizeless,
+            "unknown_assets": self.unknown_assets,
+        })
+    }
+}
+
+/// Converts a save into a DOM whose root is a single `Model`, using the default options.
+pub fn convert_save(save: &SaveData) -> WeakDom {
+    WeakDom::new(convert_model(save, "Model", &ConvertOptions::default()).model)
+}
+
+/// Converts every brick in a save into children of a `Model` named `name`.
+pub fn convert_model(save: &SaveData, name: &str, options: &ConvertOptions) -> Conversion {
+    let mut model = InstanceBuilder::new("Model");
+    model.set_name(name);
+
+    warn_invalid_indices(save);
+
+    // save metadata, so studio tooling can read it without parsing the attribution script
+    model.add_property(
+        "Attributes",
+        Attributes::new()
+            .with("BrsDescription", save.header1.description.as_str())
+            .with("BrsAuthor", save.header1.author.name.as_str())
+            .with("BrsVersion", save.version as f64),
+    );
+
+    // attribution script
+    if options.script && !options.parts_only {
+        model.add_child(
+            InstanceBuilder::new("Script")
+                .with_name("brs2rbxl")
+                .with_property(
+                    "Source",
+                    format!(
+                        "print'\"{}\"'print'Saved by {}'print''print'Exported from Brickadia with rbxl-brs'",
+                        save.header1.description, save.header1.author.name
+                    ),
+                ),
+        );
+    }
+
+    let centered;
+    let center = options.center.then(|| center_of(save, options)).flatten();
+    let options = if let Some(center) = center {
+        centered = ConvertOptions {
+            transform: options.transform.clone() * center,
+            ..options.clone()
+        };
+        &centered
+    } else {
+        options
+    };
+
+    if !options.parts_only {
+        model.add_child(metadata(save));
+    }
+
+    // bricks convert independently of each other, so do that in parallel and only attach the
+    // results to the model (which has to happen in order) sequentially
+    let converted: Vec<_> = save
+        .bricks
+        .par_iter()
+        .enumerate()
+        .filter(|(_, brick)| options.filter.matches(brick, save))
+        .map(|(index, brick)| {
+            let asset = asset_name(save, brick).unwrap_or("<invalid asset>");
+            let name = format!(
+                "{} (dir {}, rot {})",
+                asset, brick.direction as u8, brick.rotation as u8
+            );
+            let group = options.group_by.map(|g| g.key(save, brick));
+            let instances = convert_brick(brick, save, options);
+            // sizes are half extents
+            let volume = match brick.size {
+                Size::Procedural(x, y, z) => Some(
+                    [x, y, z]
+                        .map(|v| 2.0 * v as f64 / options.scale as f64)
+                        .iter()
+                        .product::<f64>(),
+                ),
+                Size::Empty => None,
+            };
+
+            if options.verbose {
+                let result = match &instances {
+                    Some(instances) if !instances.is_empty() => {
+                        format!("{} parts", instances.len())
+                    }
+                    _ => "SKIPPED".to_string(),
+                };
+                // formatted here but printed in order below
+                let line = format!(
+                    "brick {index}: {asset} dir {:?} rot {:?} at {:?}: {result}",
+                    brick.direction, brick.rotation, brick.position
+                );
+                (asset, name, group, instances, volume, Some(line))
+            } else {
+                (asset, name, group, instances, volume, None)
+            }
+        })
+        .collect();
+
+    let mut report = Report {
+        total: converted.len(),
+        ..Default::default()
+    };
+
+    // sub-models for --group-by, added to the root model once every brick is in one
+    let mut groups: BTreeMap<String, InstanceBuilder> = BTreeMap::new();
+
+    for (asset, name, group, instances, volume, line) in converted {
+        if let Some(line) = line {
+            eprintln!("{line}");
+        }
+
+        match instances {
+            Some(instances) if instances.is_empty() => {
+                report.skipped += 1;
+                report.empty += 1;
+            }
+            Some(instances) => {
+                report.converted += 1;
+                match volume {
+                    Some(volume) => report.brick_volume += volume,
+                    None => report.sizeless += 1,
+                }
+                let parent = match group {
+                    Some(group) => groups
+                        .entry(group)
+                        .or_insert_with_key(|group| InstanceBuilder::new("Model").with_name(group)),
+                    None => &mut model,
+                };
+                if instances.len() == 1 {
+                    let child = instances.into_iter().next().unwrap();
+                    parent.add_child(child.with_name(name));
+                } else {
+                    let mut group = InstanceBuilder::new("Model").with_name(name);
+                    instances.into_iter().for_each(|i| group.add_child(i));
+                    // LevelOfDetail Disabled (2), so studio never swaps the group for a
+                    // streamed imposter mesh
+                    if options.lod {
+                        group.add_property("LevelOfDetail", Enum::from_u32(2));
+                    }
+                    if options.selection_boxes {
+                        let adornee = group.referent();
+                        group.add_child(
+                            InstanceBuilder::new("SelectionBox").with_property("Adornee", adornee),
+                        );
+                    }
+                    parent.add_child(group);
+                }
+            }
+            None => {
+                report.skipped += 1;
+                *report.unknown_assets.entry(asset.to_string()).or_default() += 1;
+            }
+        };
+    }
+
+    model.add_children(groups.into_values());
+
+    Conversion { model, report }
+}
+
+/// The classes of the instances each known asset converts to, found by converting a 2x2 brick of
+/// each with the default options.
+pub fn asset_mapping() -> BTreeMap<&'static str, Vec<String>> {
+    let mut save = SaveData::default();
+    save.header2.brick_assets = ASSETS.iter().map(|asset| asset.to_string()).collect();
+    save.header2.colors = vec![Color {
+        r: 255,
+        g: 255,
+        b: 255,
+        a: 255,
+    }];
+    save.header2.materials = vec!["BMC_Plastic".into()];
+
+    ASSETS
+        .iter()
+        .enumerate()
+        .map(|(i, &asset)| {
+            let brick = Brick {
+                asset_name_index: i as u32,
+                size: if asset.starts_with("PB_") {
+                    Size::Procedural(10, 10, 6)
+                } else {
+                    Size::Empty
+                },
+                ..Default::default()
+            };
+            let classes = convert_brick(&brick, &save, &ConvertOptions::default())
+                .unwrap_or_default()
+                .into_iter()
+                .map(|instance| {
+                    let dom = WeakDom::new(instance);
+                    dom.root().class.clone()
+                })
+                .collect();
+            (asset, classes)
+        })
+        .collect()
+}
+
+/// A `Metadata` folder with a `StringValue` for each field of the save's first header.
+fn metadata(save: &SaveData) -> InstanceBuilder {
+    let header = &save.header1;
+    let mut fields = vec![
+        ("Map", header.map.clone()),
+        ("Description", header.description.clone()),
+        ("Author", header.author.name.clone()),
+        ("AuthorId", header.author.id.to_string()),
+    ];
+    if let Some(host) = &header.host {
+        fields.push(("Host", host.name.clone()));
+        fields.push(("HostId", host.id.to_string()));
+    }
+    fields.push(("Datetime", format_save_time(header.save_time)));
+    fields.push(("BrickCount", header.brick_count.to_string()));
+
+    InstanceBuilder::new("Folder")
+        .with_name("Metadata")
+        .with_children(fields.into_iter().map(|(name, value)| {
+            InstanceBuilder::new("StringValue")
+                .with_name(name)
+                .with_property("Value", value)
+        }))
+}
+
+/// Formats a save time, which is an unreal `FDateTime` (100ns ticks since 0001-01-01), as an ISO
+/// 8601 UTC timestamp.
+fn format_save_time(save_time: [u8; 8]) -> String {
+    const UNIX_EPOCH_TICKS: i64 = 621_355_968_000_000_000;
+    let seconds = (i64::from_le_bytes(save_time) - UNIX_EPOCH_TICKS).div_euclid(10_000_000);
+    let (days, time) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+    // days since the unix epoch to a civil date, see
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Warns once about each out of range asset, color and material index in the save. The part
+/// module quietly skips bricks with bad assets and falls back to white and plastic for the rest,
+/// since it runs once per brick.
+fn warn_invalid_indices(save: &SaveData) {
+    let mut assets = BTreeSet::new();
+    let mut colors = BTreeSet::new();
+    let mut materials = BTreeSet::new();
+    for brick in save.bricks.iter() {
+        if brick.asset_name_index as usize >= save.header2.brick_assets.len() {
+            assets.insert(brick.asset_name_index);
+        }
+        if let BrickColor::Index(idx) = brick.color {
+            if idx as usize >= save.header2.colors.len() {
+                colors.insert(idx);
+            }
+        }
+        if brick.material_index as usize >= save.header2.materials.len() {
+            materials.insert(brick.material_index);
+        }
+    }
+
+    for idx in assets {
+        eprintln!("Warning: asset index {idx} is out of range, skipping its bricks");
+    }
+    for idx in colors {
+        eprintln!("Warning: color index {idx} is out of range, using white");
+    }
+    for idx in materials {
+        eprintln!("Warning: material index {idx} is out of range, using BMC_Plastic");
+    }
+}
+
+/// A translation from the center of the bounding box of every converted brick's position to the
+/// origin, in roblox studs, or `None` if no bricks pass the filter.
+fn center_of(save: &SaveData, options: &ConvertOptions) -> Option<CoordinateFrame> {
+    let mut min = [i32::MAX; 3];
+    let mut max = [i32::MIN; 3];
+    let mut any = false;
+    for brick in save
+        .bricks
+        .iter()
+        .filter(|brick| options.filter.matches(brick, save))
+    {
+        let p = [brick.position.0, brick.position.2, brick.position.1];
+        for i in 0..3 {
+            min[i] = min[i].min(p[i]);
+            max[i] = max[i].max(p[i]);
+        }
+        any = true;
+    }
+    if !any {
+        return None;
+    }
+
+    let mid = |i: usize| -(min[i] as f32 + max[i] as f32) / 2.0 / options.scale;
+    Some(CoordinateFrame::new(mid(0), mid(1), mid(2)))
+}
@@ -1,93 +1,751 @@
-use std::{collections::HashSet, fs::File, io::BufWriter};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::Path,
+};
 
-use brickadia::read::SaveReader;
-use clap::Parser;
-use part::convert_brick;
-use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use anyhow::{anyhow, bail, Context};
+use brickadia::{
+    read::SaveReader,
+    save::{Color, Preview, SaveData},
+};
+use brs2rbxl::{
+    asset_mapping, convert_model, validate, BrickFilter, CollisionMode, Conversion, ConvertOptions,
+    CoordinateFrame, GroupBy, Report,
+};
+use clap::{Parser, ValueEnum};
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    InstanceBuilder, WeakDom,
+};
 
-pub mod cframe;
-mod part;
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    /// Binary model
+    Rbxm,
+    /// XML model
+    Rbxmx,
+    /// Binary place
+    Rbxl,
+    /// XML place
+    Rbxlx,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Rbxm => "rbxm",
+            Self::Rbxmx => "rbxmx",
+            Self::Rbxl => "rbxl",
+            Self::Rbxlx => "rbxlx",
+        }
+    }
+
+    fn is_place(self) -> bool {
+        matches!(self, Self::Rbxl | Self::Rbxlx)
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Collision {
+    /// Keep each brick's player collision
+    Default,
+    /// Nothing collides
+    None,
+    /// Everything collides
+    All,
+}
+
+impl From<Collision> for CollisionMode {
+    fn from(value: Collision) -> Self {
+        match value {
+            Collision::Default => Self::Default,
+            Collision::None => Self::None,
+            Collision::All => Self::All,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum MaxBricksMode {
+    /// Refuse to convert the save
+    Error,
+    /// Convert only the first bricks, up to the limit
+    Truncate,
+}
 
 #[derive(Parser)]
 #[command(
     author = "voximity",
     version = "1.0",
-    about = "Convert between Brickadia .brs files and Roblox .rbxm files"
+    about = "Convert between Brickadia .brs files and Roblox model and place files"
 )]
 struct Cli {
-    input: String,
-    #[arg(short = 'o')]
+    /// Saves to convert, or - to read one from stdin. Several saves are merged into one model
+    #[arg(required_unless_present_any = ["batch", "dump_mapping"])]
+    inputs: Vec<String>,
+    /// Output path, or - to write to stdout
+    #[arg(short = 'o', conflicts_with = "batch")]
     output: Option<String>,
+    /// Convert every .brs file in a directory, or every file matching a glob pattern, next to the
+    /// original
+    #[arg(long, value_name = "DIR|GLOB", conflicts_with = "inputs")]
+    batch: Option<String>,
+    /// Write outputs into this directory instead of next to the inputs, creating it if needed
+    #[arg(long, value_name = "DIR", conflicts_with = "output")]
+    output_dir: Option<String>,
+    /// Output file format
+    #[arg(long, value_enum, default_value_t = Format::Rbxm)]
+    format: Format,
+    /// Brickadia units per Roblox stud
+    #[arg(long, default_value_t = 10.0, value_parser = parse_scale)]
+    scale: f32,
+    /// Translate the whole model by x,y,z Roblox studs
+    #[arg(long, value_name = "X,Y,Z", allow_hyphen_values = true, value_parser = parse_vector)]
+    offset: Option<[f32; 3]>,
+    /// Round part positions to a multiple of this many studs. 0 disables
+    #[arg(long, value_name = "STUDS", default_value_t = 0.0, value_parser = parse_non_negative)]
+    snap: f32,
+    /// Row-major 4x4 matrix (16 values) applied to every brick
+    #[arg(
+        long,
+        num_args = 16,
+        value_name = "M",
+        allow_negative_numbers = true,
+        value_parser = parse_finite
+    )]
+    transform_matrix: Option<Vec<f32>>,
+    /// Tag PB_DefaultBricks at most N studs thick as BRS_WallPanel (0 disables)
+    #[arg(long, value_name = "N", default_value_t = 0.0)]
+    hollow_detection_threshold: f32,
+    /// Only convert bricks whose asset name matches this glob pattern or substring (repeatable)
+    #[arg(long, value_name = "PATTERN")]
+    filter_asset: Vec<String>,
+    /// Convert the bricks that don't match --filter-asset instead
+    #[arg(long, requires = "filter_asset")]
+    exclude: bool,
+    /// Only convert bricks with one of these materials, e.g. BMC_Glow,BMC_Glass
+    #[arg(long, value_name = "NAMES", value_delimiter = ',')]
+    filter_material: Vec<String>,
+    /// Only convert bricks of this color, as R,G,B or a hex code
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    filter_color: Option<[u8; 3]>,
+    /// How far each channel may be from --filter-color
+    #[arg(
+        long,
+        value_name = "0..255",
+        default_value_t = 0,
+        requires = "filter_color"
+    )]
+    color_tolerance: u8,
+    /// Don't convert light components
+    #[arg(long)]
+    no_lighting: bool,
+    /// Make every part at least this transparent
+    #[arg(long, value_name = "0..1", default_value_t = 0.0, value_parser = parse_unit)]
+    min_transparency: f32,
+    /// Make every part at most this transparent
+    #[arg(long, value_name = "0..1", default_value_t = 1.0, value_parser = parse_unit)]
+    max_transparency: f32,
+    /// Which parts collide
+    #[arg(long, value_enum, default_value_t = Collision::Default)]
+    collision_mode: Collision,
+    /// Nothing collides, the same as --collision-mode none
+    #[arg(long, conflicts_with = "collision_mode")]
+    no_collision: bool,
+    /// Leave parts unanchored so the build is simulated
+    #[arg(long)]
+    no_anchor: bool,
+    /// Put each player's bricks in a model named after them
+    #[arg(long)]
+    group_by_owner: bool,
+    /// Put bricks in a model per material
+    #[arg(long, conflicts_with = "group_by_owner")]
+    group_by_material: bool,
+    /// Put bricks in a model per color, named by its hex code
+    #[arg(long, conflicts_with_all = ["group_by_owner", "group_by_material"])]
+    group_by_color: bool,
+    /// Don't add the attribution script to the model
+    #[arg(long)]
+    no_script: bool,
+    /// Only write parts: no attribution script, metadata, lights or sounds
+    #[arg(long)]
+    parts_only: bool,
+    /// Limit on how many bricks a save may have
+    #[arg(long, value_name = "N")]
+    max_bricks: Option<usize>,
+    /// What to do with a save that has more bricks than --max-bricks
+    #[arg(long, value_enum, default_value_t = MaxBricksMode::Error, requires = "max_bricks")]
+    max_bricks_mode: MaxBricksMode,
+    /// Fail if any brick has no converter, or if nothing was converted at all
+    #[arg(long)]
+    strict: bool,
+    /// Outline each brick that converts to several parts with a SelectionBox
+    #[arg(long)]
+    selection_boxes: bool,
+    /// Keep bricks that convert to several parts from being streamed out at a distance
+    #[arg(long)]
+    lod: bool,
+    /// Print how each brick converted to stderr
+    #[arg(short, long)]
+    verbose: bool,
+    /// Also emit a translucent magenta box over each brick's bounds
+    #[arg(long)]
+    debug_bounds: bool,
+    /// Move the center of the build to the origin
+    #[arg(long)]
+    center: bool,
+    /// Override colors in each save's palette with the ones in this JSON file, an object mapping
+    /// palette indices to {"r", "g", "b", "a"} objects
+    #[arg(long, value_name = "PATH")]
+    color_palette_file: Option<String>,
+    /// Write the roblox classes each known brick asset converts to to this path as JSON. Inputs
+    /// are optional with this
+    #[arg(long, value_name = "PATH")]
+    dump_mapping: Option<String>,
+    /// Also write the conversion report to this path as JSON
+    #[arg(long, value_name = "PATH", conflicts_with = "batch")]
+    report: Option<String>,
+    /// Read the written model back in and check that it's sane
+    #[arg(long)]
+    validation_mode: bool,
+    /// Convert and print the report without writing anything; fails if any bricks were skipped
+    #[arg(long)]
+    dry_run: bool,
 }
 
-fn main() {
+fn parse_finite(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        Ok(_) => Err(format!("{s} is not a finite number")),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_scale(s: &str) -> Result<f32, String> {
+    match parse_finite(s)? {
+        value if value > 0.0 => Ok(value),
+        _ => Err("scale must be positive".into()),
+    }
+}
+
+fn parse_non_negative(s: &str) -> Result<f32, String> {
+    match parse_finite(s)? {
+        value if value >= 0.0 => Ok(value),
+        _ => Err("must not be negative".into()),
+    }
+}
+
+fn parse_unit(s: &str) -> Result<f32, String> {
+    match parse_finite(s)? {
+        value if (0.0..=1.0).contains(&value) => Ok(value),
+        _ => Err("must be between 0 and 1".into()),
+    }
+}
+
+fn parse_vector(s: &str) -> Result<[f32; 3], String> {
+    let values = s
+        .split(',')
+        .map(|v| parse_finite(v.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    values
+        .try_into()
+        .map_err(|_| "expected three comma-separated values".to_string())
+}
+
+fn parse_color(s: &str) -> Result<[u8; 3], String> {
+    if s.contains(',') {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<u8>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        return values
+            .try_into()
+            .map_err(|_| "expected three comma-separated values".to_string());
+    }
+
+    // from_str_radix would also take a sign, so check the digits first
+    let hex = s.trim_start_matches('#');
+    match u32::from_str_radix(hex, 16) {
+        Ok(value) if hex.len() == 6 && hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            Ok([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+        }
+        _ => Err(format!("{s} is not an R,G,B color or a hex code")),
+    }
+}
+
+fn print_report(log: &mut dyn Write, report: &Report) -> std::io::Result<()> {
+    writeln!(
+        log,
+        "Converted {} of {} bricks ({} skipped).",
+        report.converted, report.total, report.skipped
+    )?;
+
+    if report.empty > 0 {
+        writeln!(log, "{} procedural bricks had no size.", report.empty)?;
+    }
+
+    if !report.unknown_assets.is_empty() {
+        writeln!(log, "Unimplemented brick types:")?;
+        for (asset, count) in report.unknown_assets.iter() {
+            writeln!(log, "* {} ({})", asset, count)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the volume of every part in the output, as a sanity check against the volume of the
+/// bricks they came from.
+fn print_volume(log: &mut dyn Write, dom: &WeakDom, report: &Report) -> std::io::Result<()> {
+    let volume = dom
+        .descendants()
+        .filter(|instance| instance.name != "DebugBounds")
+        .filter_map(|instance| match instance.properties.get("Size") {
+            Some(Variant::Vector3(size)) => Some(size.x as f64 * size.y as f64 * size.z as f64),
+            _ => None,
+        })
+        // an empty f64 sum is -0.0, which would print as "-0.00"
+        .fold(0.0, |total, volume| total + volume);
+
+    write!(log, "Total part volume: {:.2} studs³", volume)?;
+    // only comparable when every brick's volume is known
+    if report.sizeless == 0 && report.brick_volume > 0.0 {
+        write!(
+            log,
+            " ({:.1}% of the bricks' volume)",
+            volume / report.brick_volume * 100.0
+        )?;
+    }
+    writeln!(log)
+}
+
+fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
-    let out = cli.output.unwrap_or_else(|| cli.input.clone() + ".rbxm");
-
-    let save = SaveReader::new(File::open(cli.input.as_str()).unwrap())
-        .unwrap()
-        .read_all_skip_preview()
-        .unwrap();
-
-    let mut model = InstanceBuilder::new("Model");
-    model.set_name(cli.input.as_str());
-
-    // attribution script
-    model.add_child(
-        InstanceBuilder::new("Script")
-            .with_name("brs2rbxl")
-            .with_property(
-                "Source",
-                format!(
-                    "print'\"{}\"'print'Saved by {}'print''print'Exported from Brickadia with rbxl-brs'",
-                    save.header1.description, save.header1.author.name
-                ),
-            ),
-    );
 
-    let mut missing_assets: HashSet<&str> = HashSet::new();
-    let mut missing_bricks = 0u32;
+    let mut options = ConvertOptions {
+        scale: cli.scale,
+        offset: cli.offset.unwrap_or_default(),
+        hollow_detection_threshold: cli.hollow_detection_threshold,
+        center: cli.center,
+        lights: !cli.no_lighting,
+        min_transparency: cli.min_transparency,
+        max_transparency: cli.max_transparency,
+        collision: if cli.no_collision {
+            CollisionMode::None
+        } else {
+            cli.collision_mode.into()
+        },
+        anchored: !cli.no_anchor,
+        parts_only: cli.parts_only,
+        script: !cli.no_script,
+        debug_bounds: cli.debug_bounds,
+        verbose: cli.verbose,
+        snap: cli.snap,
+        selection_boxes: cli.selection_boxes,
+        lod: cli.lod,
+        group_by: if cli.group_by_owner {
+            Some(GroupBy::Owner)
+        } else if cli.group_by_material {
+            Some(GroupBy::Material)
+        } else if cli.group_by_color {
+            Some(GroupBy::Color)
+        } else {
+            None
+        },
+        filter: BrickFilter {
+            assets: cli.filter_asset.clone(),
+            exclude_assets: cli.exclude,
+            materials: cli.filter_material.clone(),
+            color: cli.filter_color,
+            color_tolerance: cli.color_tolerance,
+        },
+        ..Default::default()
+    };
+    if cli.min_transparency > cli.max_transparency {
+        bail!("--min-transparency can't be more than --max-transparency");
+    }
+    if let Some(values) = cli.transform_matrix.clone() {
+        let values: [f32; 16] = values
+            .try_into()
+            .map_err(|_| anyhow!("--transform-matrix takes 16 values"))?;
+        options.transform = CoordinateFrame::from_array_16(values);
+        if !options.transform.is_rigid() {
+            eprintln!(
+                "Warning: --transform-matrix is not a pure rotation and translation; \
+                 the resulting CFrames may not be valid in Roblox."
+            );
+        }
+    }
+
+    let palette = match &cli.color_palette_file {
+        Some(path) => read_palette(path)?,
+        None => Palette::new(),
+    };
+
+    if let Some(dir) = &cli.output_dir {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create output directory '{}'", dir))?;
+    }
+
+    if let Some(path) = &cli.dump_mapping {
+        std::fs::write(path, serde_json::to_string_pretty(&asset_mapping())?)
+            .with_context(|| format!("failed to write mapping '{}'", path))?;
+        if cli.inputs.is_empty() && cli.batch.is_none() {
+            return Ok(());
+        }
+    }
+
+    match &cli.batch {
+        Some(pattern) => convert_batch(&cli, &options, &palette, pattern),
+        None => convert(&cli, &options, &palette, &cli.inputs, cli.output.clone()),
+    }
+}
+
+/// Palette colors to use instead of the ones in the save, by index.
+type Palette = BTreeMap<usize, Color>;
+
+fn read_palette(path: &str) -> anyhow::Result<Palette> {
+    let file =
+        File::open(path).with_context(|| format!("failed to open palette file '{}'", path))?;
+    let json: serde_json::Value = serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("failed to parse palette file '{}'", path))?;
+    let entries = json
+        .as_object()
+        .ok_or_else(|| anyhow!("palette file '{}' must be a JSON object", path))?;
+
+    entries
+        .iter()
+        .map(|(index, color)| {
+            let index = index
+                .parse()
+                .map_err(|_| anyhow!("palette index '{}' is not a number", index))?;
+            let channel = |name: &str, default: Option<u8>| match color.get(name) {
+                Some(value) => value
+                    .as_u64()
+                    .and_then(|v| u8::try_from(v).ok())
+                    .ok_or_else(|| anyhow!("palette color {index} has an invalid '{name}'")),
+                None => default.ok_or_else(|| anyhow!("palette color {index} has no '{name}'")),
+            };
+
+            Ok((
+                index,
+                Color {
+                    r: channel("r", None)?,
+                    g: channel("g", None)?,
+                    b: channel("b", None)?,
+                    a: channel("a", Some(255))?,
+                },
+            ))
+        })
+        .collect()
+}
+
+fn convert_batch(
+    cli: &Cli,
+    options: &ConvertOptions,
+    palette: &Palette,
+    pattern: &str,
+) -> anyhow::Result<()> {
+    let pattern = if Path::new(pattern).is_dir() {
+        format!("{}/*.brs", glob::Pattern::escape(pattern))
+    } else {
+        pattern.to_string()
+    };
+
+    let mut total = 0;
+    let mut failed = 0;
+    for entry in glob::glob(&pattern).with_context(|| format!("invalid pattern '{pattern}'"))? {
+        total += 1;
+        let result = entry.context("failed to read directory").and_then(|path| {
+            let input = path.to_string_lossy();
+            println!("{input}:");
+            convert(cli, options, palette, &[input.into_owned()], None)
+        });
+
+        if let Err(e) = result {
+            failed += 1;
+            eprintln!("Error: {e:#}");
+        }
+    }
+
+    println!("Converted {} of {} saves.", total - failed, total);
+    if failed > 0 {
+        bail!("{failed} saves failed to convert");
+    }
+
+    Ok(())
+}
+
+fn read_save(cli: &Cli, input: &str, palette: &Palette) -> anyhow::Result<SaveData> {
+    let reader: Box<dyn Read> = if input == "-" {
+        Box::new(std::io::stdin().lock())
+    } else {
+        Box::new(
+            File::open(input).with_context(|| format!("failed to open input file '{}'", input))?,
+        )
+    };
 
-    for brick in save.bricks.iter() {
-        let asset = save.header2.brick_assets[brick.asset_name_index as usize].as_str();
+    // the whole save has to be read at once, brickadia can't read bricks incrementally. the
+    // sections are read one by one so that a save with too many bricks can be turned away before
+    // they are
+    let context = || format!("failed to read save '{}'", input);
+    let mut reader = SaveReader::new(reader).with_context(context)?;
+    let header1 = reader.read_header1().with_context(context)?;
+    if let (Some(max), MaxBricksMode::Error) = (cli.max_bricks, cli.max_bricks_mode) {
+        if header1.brick_count as usize > max {
+            bail!(
+                "save '{}' has {} bricks, more than the --max-bricks limit of {}",
+                input,
+                header1.brick_count,
+                max
+            );
+        }
+    }
+    let header2 = reader.read_header2().with_context(context)?;
+    reader.skip_preview().with_context(context)?;
+    let (bricks, components) = reader
+        .read_bricks(&header1, &header2)
+        .with_context(context)?;
 
-        let name = format!(
-            "{} (dir {}, rot {})",
-            asset, brick.direction as u8, brick.rotation as u8
-        );
+    let mut save = SaveData {
+        version: reader.version,
+        game_version: reader.game_version,
+        header1,
+        header2,
+        preview: Preview::None,
+        bricks,
+        components,
+    };
+    if let Some(max) = cli.max_bricks {
+        save.bricks.truncate(max);
+    }
 
-        match convert_brick(brick, &save) {
-            Some(instances) => {
-                if instances.len() == 1 {
-                    let child = instances.into_iter().next().unwrap();
-                    model.add_child(child.with_name(name));
-                } else {
-                    let mut group = InstanceBuilder::new("Model").with_name(name);
-                    instances.into_iter().for_each(|i| group.add_child(i));
-                    model.add_child(group);
-                }
-            }
-            None => {
-                missing_bricks += 1;
-                missing_assets.insert(asset);
-            }
-        };
+    // palettes can be overridden past the end of the save's own, any gap is left white
+    let colors = &mut save.header2.colors;
+    for (&index, color) in palette {
+        if index >= colors.len() {
+            colors.resize(
+                index + 1,
+                Color {
+                    r: 255,
+                    g: 255,
+                    b: 255,
+                    a: 255,
+                },
+            );
+        }
+        colors[index] = color.clone();
     }
 
-    if missing_assets.len() > 0 {
-        println!(
-            "Failed to convert {} brick types ({} bricks total).",
-            missing_assets.len(),
-            missing_bricks
-        );
+    Ok(save)
+}
 
-        for asset in missing_assets {
-            println!("* {}", asset);
+fn convert(
+    cli: &Cli,
+    options: &ConvertOptions,
+    palette: &Palette,
+    inputs: &[String],
+    output: Option<String>,
+) -> anyhow::Result<()> {
+    let name = match inputs {
+        [input] if input == "-" => "stdin",
+        [input] => input.as_str(),
+        _ => "merged",
+    };
+    let out = match (output, &cli.output_dir) {
+        (Some(output), _) => output,
+        (None, Some(dir)) => {
+            let file_name = Path::new(name)
+                .file_name()
+                .map_or(name.into(), |n| n.to_string_lossy());
+            Path::new(dir)
+                .join(format!("{}.{}", file_name, cli.format.extension()))
+                .to_string_lossy()
+                .into_owned()
         }
+        (None, None) => format!("{}.{}", name, cli.format.extension()),
+    };
+
+    // a single save's bricks go straight into the root model, several saves each get their own
+    // model under it
+    let (model, report, expected_children) = if let [input] = inputs {
+        let save = read_save(cli, input, palette)?;
+        let Conversion { model, report } = convert_model(&save, name, options);
+        // grouped bricks aren't direct children, so there's nothing to compare against
+        let expected = options.group_by.is_none().then_some(report.converted);
+        (model, report, expected)
+    } else {
+        let mut model = InstanceBuilder::new("Model").with_name(name);
+        let mut report = Report::default();
+        for input in inputs {
+            let save = read_save(cli, input, palette)?;
+            let file_name = Path::new(input)
+                .file_name()
+                .map_or(input.clone(), |n| n.to_string_lossy().into_owned());
+            let conversion = convert_model(&save, &file_name, options);
+            model.add_child(conversion.model);
+            report.merge(conversion.report);
+        }
+        (model, report, Some(inputs.len()))
+    };
+
+    // places are a DataModel whose children are the services, so the model goes in Workspace
+    let dom = if cli.format.is_place() {
+        WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("Workspace").with_child(model)),
+        )
+    } else {
+        WeakDom::new(model)
+    };
+    let roots = if cli.format.is_place() {
+        dom.root().children().to_vec()
+    } else {
+        vec![dom.root_ref()]
+    };
+
+    // when the model goes to stdout, everything else has to go to stderr so it doesn't end up in
+    // the middle of the file
+    let to_stdout = out == "-";
+    let mut log: Box<dyn Write> = if to_stdout {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    };
+
+    // the model would be written with nothing in it besides the script and metadata. --strict
+    // refuses to write it at all below
+    if report.converted == 0 {
+        eprintln!("Warning: No bricks were successfully converted.");
     }
 
-    let dom = WeakDom::new(model);
+    print_report(&mut log, &report)?;
+    print_volume(&mut log, &dom, &report)?;
+    if let Some(path) = &cli.report {
+        std::fs::write(path, serde_json::to_string_pretty(&report.to_json())?)
+            .with_context(|| format!("failed to write report '{}'", path))?;
+    }
 
-    let writer = BufWriter::new(File::create(out).unwrap());
-    rbx_binary::to_writer(writer, &dom, &[dom.root_ref()]).unwrap();
+    if cli.strict {
+        let unknown: usize = report.unknown_assets.values().sum();
+        if unknown > 0 {
+            let assets: Vec<_> = report.unknown_assets.keys().map(String::as_str).collect();
+            bail!(
+                "{} bricks have no converter: {}",
+                unknown,
+                assets.join(", ")
+            );
+        }
+        if report.converted == 0 {
+            bail!("no bricks were converted");
+        }
+    }
+
+    if cli.dry_run {
+        if report.skipped > 0 {
+            bail!("{} bricks would be skipped", report.skipped);
+        }
+        return Ok(());
+    }
+
+    let out = Path::new(&out);
+    if to_stdout {
+        let mut writer = BufWriter::new(std::io::stdout().lock());
+        serialize(&mut writer, &dom, &roots, cli.format)?;
+        writer.flush().context("failed to write the model")?;
+    } else {
+        write_output(&dom, &roots, out, cli.format)?;
+    }
+
+    if cli.validation_mode && cli.format != Format::Rbxm {
+        eprintln!("Warning: --validation-mode only supports rbxm output, skipping.");
+    } else if cli.validation_mode && to_stdout {
+        eprintln!("Warning: --validation-mode can't read the model back from stdout, skipping.");
+    } else if cli.validation_mode {
+        match (validate::validate_rbxm(out), expected_children) {
+            (Ok(report), Some(expected)) if report.bricks != expected => bail!(
+                "validation failed: expected {} bricks in the output, found {}",
+                expected,
+                report.bricks
+            ),
+            (Ok(report), _) => writeln!(log, "Validated {} instances.", report.instances)?,
+            (Err(e), _) => return Err(e).context("validation failed"),
+        }
+    }
+
+    Ok(())
+}
+
+fn write_output(dom: &WeakDom, roots: &[Ref], out: &Path, format: Format) -> anyhow::Result<()> {
+    // write to a sibling temp file and only move it into place once it's complete, so an
+    // interrupted conversion never leaves a truncated model behind. the temp file is removed
+    // when dropped if we bail out early
+    let dir = match out.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut builder = tempfile::Builder::new();
+    // temp files are private by default, but this one becomes the output
+    #[cfg(unix)]
+    builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o666));
+    let file = builder
+        .tempfile_in(dir)
+        .with_context(|| format!("failed to create a temp file in '{}'", dir.display()))?;
+
+    let mut writer = BufWriter::new(file.as_file());
+    serialize(&mut writer, dom, roots, format)?;
+    writer.flush().context("failed to write the model")?;
+    drop(writer);
+
+    file.persist(out)
+        .with_context(|| format!("failed to write output file '{}'", out.display()))?;
+
+    Ok(())
+}
+
+fn serialize(
+    writer: impl Write,
+    dom: &WeakDom,
+    roots: &[Ref],
+    format: Format,
+) -> anyhow::Result<()> {
+    match format {
+        Format::Rbxm | Format::Rbxl => {
+            rbx_binary::to_writer(writer, dom, roots).context("failed to serialize the model")?
+        }
+        Format::Rbxmx | Format::Rbxlx => rbx_xml::to_writer_default(writer, dom, roots)
+            .context("failed to serialize the model")?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_write_keeps_existing_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("model.rbxm");
+        std::fs::write(&out, b"previous model").unwrap();
+
+        // a root that isn't in the dom can't be serialized
+        let dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let result = write_output(&dom, &[Ref::new()], &out, Format::Rbxm);
+
+        assert!(result.is_err());
+        assert_eq!(std::fs::read(&out).unwrap(), b"previous model");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_color("#0fFf80"), Ok([0x0f, 0xff, 0x80]));
+        assert_eq!(parse_color("10, 20,30"), Ok([10, 20, 30]));
+        assert!(parse_color("+FFFFF").is_err());
+        assert!(parse_color("#FFF").is_err());
+    }
 }
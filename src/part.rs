@@ -1,15 +1,12 @@
-use std::{
-    collections::{hash_map::Entry, HashMap},
-    f32::consts::PI,
-};
+use std::{collections::HashMap, f32::consts::PI};
 
 use brickadia::save::{Brick, BrickColor, Color, SaveData, Size, UnrealType};
 use rbx_dom_weak::{
-    types::{CFrame, Color3, Enum, Variant, Vector3},
+    types::{Attributes, CFrame, Color3, Content, Enum, Tags, UDim, UDim2, Variant, Vector3},
     InstanceBuilder,
 };
 
-use crate::cframe::CoordinateFrame;
+use crate::{cframe::CoordinateFrame, filter::BrickFilter};
 
 macro_rules! rm {
     (
@@ -21,17 +18,28 @@ macro_rules! rm {
     };
 }
 
+/// Reads a component property of the given `UnrealType` variant, or `$default` when it's missing.
+/// A property of some other type is most likely a save format change, so that is warned about.
 macro_rules! component_property {
     ($component:ident, $field:expr, $variant:path, $default:expr) => {
-        if let Some($variant(_inner)) = $component.get($field) {
-            _inner
-        } else {
-            $default
+        match $component.get($field) {
+            Some($variant(_inner)) => _inner,
+            Some(other) => {
+                eprintln!(
+                    "Warning: component property {} should be {} but is {:?}, using the default",
+                    $field,
+                    stringify!($variant),
+                    other
+                );
+                $default
+            }
+            None => $default,
         }
     };
 }
 
-static ORIENTATION_MAP: [[f32; 9]; 24] = [
+/// Row-major rotation matrices for each brick orientation, indexed by `direction << 2 | rotation`.
+pub static ORIENTATION_MAP: [[f32; 9]; 24] = [
     rm!(r(0.0, -1.0, 0.0), u(1.0, 0.0, 0.0), f(0.0, 0.0, -1.0)),
     rm!(r(0.0, 0.0, 1.0), u(1.0, 0.0, 0.0), f(0.0, -1.0, 0.0)),
     rm!(r(0.0, 1.0, 0.0), u(1.0, 0.0, 0.0), f(0.0, 0.0, 1.0)),
@@ -58,12 +66,164 @@ static ORIENTATION_MAP: [[f32; 9]; 24] = [
     rm!(r(0.0, 0.0, 1.0), u(0.0, -1.0, 0.0), f(-1.0, 0.0, 0.0)),
 ];
 
+// every entry must be a proper rotation: unit length rows, perpendicular to each other, and a
+// determinant of 1. checked at compile time so a typo in the table fails the build
+const _: () = {
+    const EPSILON: f32 = 1e-5;
+    let mut i = 0;
+    while i < ORIENTATION_MAP.len() {
+        let m = &ORIENTATION_MAP[i];
+
+        let mut a = 0;
+        while a < 3 {
+            let mut b = 0;
+            while b < 3 {
+                let dot =
+                    m[a * 3] * m[b * 3] + m[a * 3 + 1] * m[b * 3 + 1] + m[a * 3 + 2] * m[b * 3 + 2];
+                let expected = if a == b { 1.0 } else { 0.0 };
+                assert!(
+                    (dot - expected).abs() < EPSILON,
+                    "ORIENTATION_MAP rows aren't orthonormal"
+                );
+                b += 1;
+            }
+            a += 1;
+        }
+
+        let det = m[0] * (m[4] * m[8] - m[5] * m[7]) - m[1] * (m[3] * m[8] - m[5] * m[6])
+            + m[2] * (m[3] * m[7] - m[4] * m[6]);
+        assert!(
+            (det - 1.0).abs() < EPSILON,
+            "ORIENTATION_MAP has a reflection"
+        );
+
+        i += 1;
+    }
+};
+
+/// How to split bricks into sub-models of the root model.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    /// One model per player, named after them.
+    Owner,
+    /// One model per material, e.g. `BMC_Glow`.
+    Material,
+    /// One model per color, named by its hex code, e.g. `#FF0000`.
+    Color,
+}
+
+impl GroupBy {
+    /// The name of the group `brick` belongs in.
+    pub fn key(self, save: &SaveData, brick: &Brick) -> String {
+        match self {
+            Self::Owner => owner_name(save, brick).to_string(),
+            Self::Material => material_name(save, brick).to_string(),
+            Self::Color => {
+                let [r, g, b] = srgb_bytes(brick_color(save, brick));
+                format!("#{r:02X}{g:02X}{b:02X}")
+            }
+        }
+    }
+}
+
+/// Which parts collide.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionMode {
+    /// Parts collide if the brick collides with players.
+    #[default]
+    Default,
+    /// No part collides.
+    None,
+    /// Every part collides.
+    All,
+}
+
+/// Settings that apply to every converted brick.
+#[derive(Clone)]
+pub struct ConvertOptions {
+    /// Brickadia units per roblox stud. Brickadia positions are in units where a stud is 10 wide
+    /// and a plate 4 tall, and sizes are half-extents in those same units; roblox studs are 1 wide
+    /// and plates 0.4 tall. So the default of 10 divides positions by 10 and sizes by 5.
+    pub scale: f32,
+    /// Applied to each brick's frame before it is written out.
+    pub transform: CoordinateFrame,
+    /// Translation in roblox studs added to every brick after scaling and `transform`.
+    pub offset: [f32; 3],
+    /// `PB_DefaultBrick`s at most this many studs thick are tagged `BRS_WallPanel`. 0 disables.
+    pub hollow_detection_threshold: f32,
+    /// Move the center of the bounding box of every brick position to the origin, before
+    /// `transform` and `offset` are applied.
+    pub center: bool,
+    /// Convert brickadia's light components into roblox lights.
+    pub lights: bool,
+    /// Every part's transparency is clamped to at least this.
+    pub min_transparency: f32,
+    /// Every part's transparency is clamped to at most this.
+    pub max_transparency: f32,
+    /// Overrides each brick's collision.
+    pub collision: CollisionMode,
+    /// Anchor every part. Without it the build is left to physics.
+    pub anchored: bool,
+    /// Which bricks to convert.
+    pub filter: BrickFilter,
+    /// Put bricks in a sub-model per group rather than directly in the root model.
+    pub group_by: Option<GroupBy>,
+    /// Only emit geometry: no attribution script or metadata folder on the model, and no lights,
+    /// sounds, click detectors or signs on the parts.
+    pub parts_only: bool,
+    /// Add a `Script` crediting the save's author to the model.
+    pub script: bool,
+    /// Also emit a translucent magenta part over each procedural brick's bounding box, to make
+    /// conversion offsets easy to spot.
+    pub debug_bounds: bool,
+    /// Print how each brick converted to stderr.
+    pub verbose: bool,
+    /// Round every part's position to a multiple of this many studs, to get rid of float error.
+    /// 0 disables.
+    pub snap: f32,
+    /// Outline the model of each brick that converts to several parts with a `SelectionBox`.
+    pub selection_boxes: bool,
+    /// Turn level of detail off for the model of each brick that converts to several parts, so
+    /// they stay visible at any distance.
+    pub lod: bool,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            scale: 10.0,
+            transform: CoordinateFrame::identity(),
+            offset: [0.0; 3],
+            hollow_detection_threshold: 0.0,
+            center: false,
+            lights: true,
+            min_transparency: 0.0,
+            max_transparency: 1.0,
+            collision: CollisionMode::Default,
+            anchored: true,
+            filter: BrickFilter::default(),
+            group_by: None,
+            parts_only: false,
+            script: true,
+            debug_bounds: false,
+            verbose: false,
+            snap: 0.0,
+            selection_boxes: false,
+            lod: false,
+        }
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PartDef {
     class: String,
     offset: CoordinateFrame,
     size: Vector3,
     color: Option<Color>,
     properties: HashMap<String, Variant>,
+    /// A `SpecialMesh` child, as its `MeshType` and `Scale`.
+    mesh: Option<(u32, Vector3)>,
 }
 
 impl Default for PartDef {
@@ -74,6 +234,7 @@ impl Default for PartDef {
             size: Vector3::new(0.0, 0.0, 0.0),
             color: None,
             properties: HashMap::new(),
+            mesh: None,
         }
     }
 }
@@ -92,52 +253,183 @@ impl PartDef {
         self
     }
 
+    /// Moves the part along its own axes, i.e. after any rotation so far.
     pub fn offset(self, x: f32, y: f32, z: f32) -> Self {
         self.cf(CoordinateFrame::new(x, y, z))
     }
 
+    /// Moves the part along the brick's axes, regardless of how it has been rotated.
+    pub fn world_offset(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.offset = CoordinateFrame::new(x, y, z) * self.offset;
+        self
+    }
+
     pub fn size(mut self, x: f32, y: f32, z: f32) -> Self {
         self.size = Vector3::new(x, y, z);
         self
     }
 
+    /// Uniformly scales the size of this part only.
+    pub fn scale_size(mut self, factor: f32) -> Self {
+        debug_assert!(factor > 0.0, "scale factor must be positive, got {factor}");
+        self.size = Vector3::new(
+            self.size.x * factor,
+            self.size.y * factor,
+            self.size.z * factor,
+        );
+        self
+    }
+
+    /// Scales this part and its offset from the brick along each of the brick's axes. The size is
+    /// along the part's own axes, so a rotated part scales by whichever factors its axes line up
+    /// with.
+    pub fn scale(mut self, x: f32, y: f32, z: f32) -> Self {
+        debug_assert!(
+            x > 0.0 && y > 0.0 && z > 0.0,
+            "scale factors must be positive, got {x}, {y}, {z}"
+        );
+        // each of the part's axes is a column of its rotation, scaled along the brick's axes
+        let (_, rotation, _) = self.offset.decompose();
+        let factors = [x, y, z];
+        let axis = |i: usize| {
+            (0..3)
+                .map(|j| (rotation[j][i] * factors[j]).powi(2))
+                .sum::<f32>()
+                .sqrt()
+        };
+        self.size = Vector3::new(
+            self.size.x * axis(0),
+            self.size.y * axis(1),
+            self.size.z * axis(2),
+        );
+        self.offset = self.offset.scale_xyz(x, y, z);
+        self
+    }
+
+    /// Sets all six surface types at once, as `Enum::from_u32` values (0 smooth, 3 studs, 4 inlet).
+    pub fn with_surface_normals(
+        self,
+        top: u32,
+        bottom: u32,
+        left: u32,
+        right: u32,
+        front: u32,
+        back: u32,
+    ) -> Self {
+        self.property("TopSurface", Enum::from_u32(top))
+            .property("BottomSurface", Enum::from_u32(bottom))
+            .property("LeftSurface", Enum::from_u32(left))
+            .property("RightSurface", Enum::from_u32(right))
+            .property("FrontSurface", Enum::from_u32(front))
+            .property("BackSurface", Enum::from_u32(back))
+    }
+
     pub fn color(mut self, color: Color) -> Self {
         self.color = Some(color);
         self
     }
 
+    /// A copy of this part in a different color, for converters that emit the same piece twice.
+    pub fn clone_with_color(&self, color: Color) -> Self {
+        self.clone().color(color)
+    }
+
+    /// Clears the color override, so the part takes the brick's color again.
+    pub fn with_no_color(mut self) -> Self {
+        self.color = None;
+        self
+    }
+
+    /// Gives the part a `SpecialMesh` of the given `MeshType` (e.g. 4 for a cylinder). The scale is
+    /// relative to the part's size, so isn't affected by `ConvertOptions::scale`.
+    pub fn mesh(mut self, mesh_type: u32, scale: Vector3) -> Self {
+        self.mesh = Some((mesh_type, scale));
+        self
+    }
+
+    /// Sets a property on the part, written after everything `to_instance` sets itself. Anything
+    /// rbx_dom_weak can turn into a `Variant` works as a value, including `&str`.
     pub fn property<K: Into<String>, V: Into<Variant>>(mut self, key: K, value: V) -> Self {
         self.properties.insert(key.into(), value.into());
         self
     }
 
-    pub fn to_instance(self, save: &SaveData, brick: &Brick) -> InstanceBuilder {
-        let mut instance = InstanceBuilder::new(self.class);
+    /// Combines two parts into one when together they make up a single box: same class, color
+    /// and properties, the same rotation, the same cross section, and touching or overlapping end
+    /// to end along one of their axes. Otherwise both are given back unchanged.
+    #[allow(clippy::result_large_err)]
+    pub fn try_merge(a: PartDef, b: PartDef) -> Result<PartDef, (PartDef, PartDef)> {
+        const EPSILON: f32 = 1e-4;
+
+        if a.class != b.class
+            || a.color != b.color
+            || a.properties != b.properties
+            || a.mesh.is_some()
+            || b.mesh.is_some()
+        {
+            return Err((a, b));
+        }
+
+        let (ta, ra, _) = a.offset.decompose();
+        let (tb, rb, _) = b.offset.decompose();
+        let same_rotation = (0..3).all(|i| (0..3).all(|j| (ra[i][j] - rb[i][j]).abs() < EPSILON));
+        if !same_rotation {
+            return Err((a, b));
+        }
+
+        // b's center relative to a's, along a's own axes
+        let d = [tb.x - ta.x, tb.y - ta.y, tb.z - ta.z];
+        let delta: [f32; 3] = [0, 1, 2].map(|j| (0..3).map(|i| ra[i][j] * d[i]).sum());
+        let sa = [a.size.x, a.size.y, a.size.z];
+        let sb = [b.size.x, b.size.y, b.size.z];
+
+        let axis = (0..3).find(|&k| {
+            (0..3).all(|i| i == k || ((sa[i] - sb[i]).abs() < EPSILON && delta[i].abs() < EPSILON))
+                && delta[k].abs() <= (sa[k] + sb[k]) / 2.0 + EPSILON
+        });
+        let Some(k) = axis else {
+            return Err((a, b));
+        };
+
+        let lo = (-sa[k] / 2.0).min(delta[k] - sb[k] / 2.0);
+        let hi = (sa[k] / 2.0).max(delta[k] + sb[k] / 2.0);
+        let mut size = sa;
+        size[k] = hi - lo;
+        let mut shift = [0.0; 3];
+        shift[k] = (lo + hi) / 2.0;
+
+        Ok(a.size(size[0], size[1], size[2])
+            .offset(shift[0], shift[1], shift[2]))
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_instance(
+        self,
+        save: &SaveData,
+        brick: &Brick,
+        options: &ConvertOptions,
+    ) -> InstanceBuilder {
+        // converters work in studs at the default scale, so their sizes and offsets are rescaled
+        // along with the brick position
+        let factor = 10.0 / options.scale;
+        let this = self.scale(factor, factor, factor);
+
+        let mut instance = InstanceBuilder::new(this.class);
 
         // write size
-        instance.add_property("Size", self.size);
+        instance.add_property("Size", this.size);
 
         // write cframe
-        let mat_comp =
-            ORIENTATION_MAP[((brick.direction as u8) << 2 | (brick.rotation as u8)) as usize];
-
         instance.add_property(
             "CFrame",
-            CFrame::from(
-                CoordinateFrame::from_rotation(
-                    brick.position.0 as f32 / 10.0,
-                    brick.position.2 as f32 / 10.0,
-                    brick.position.1 as f32 / 10.0,
-                    mat_comp,
-                ) * self.offset,
-            ),
+            CFrame::from(snap(brick_frame(brick, options) * this.offset, options)),
         );
 
         // write color
-        let color = self.color.as_ref().unwrap_or_else(|| match &brick.color {
-            BrickColor::Index(idx) => &save.header2.colors[*idx as usize],
-            BrickColor::Unique(c) => c,
-        });
+        let color = this
+            .color
+            .as_ref()
+            .unwrap_or_else(|| brick_color(save, brick));
 
         let color_value = Color3::new(
             linear_to_srgb(color.r as f32 / 255.0),
@@ -147,79 +439,178 @@ impl PartDef {
         instance.add_property("Color", color_value);
 
         // write material
+        let material = material_name(save, brick);
+        let mut transparency = None;
         if brick.visibility {
-            match save.header2.materials[brick.material_index as usize].as_str() {
+            match material {
+                // ghost bricks are a fixed see-through glow in brickadia, intensity doesn't change
+                // how much
                 "BMC_Ghost" | "BMC_Ghost_Fail" => {
                     instance.add_property("Material", Enum::from_u32(288));
-                    instance.add_property("Transparency", 0.5);
+                    transparency = Some(0.5);
                 }
-                "BMC_Glow" => instance.add_property("Material", Enum::from_u32(288)),
+                // brickadia's default material. roblox parts already default to Plastic (256), but
+                // set it anyway so the output doesn't depend on that default
+                "BMC_Plastic" => instance.add_property("Material", Enum::from_u32(256)),
+                // classic studded plastic. converters that set their own surfaces still win, since
+                // their properties are written last
+                "BMC_Brick" => {
+                    instance.add_property("Material", Enum::from_u32(256));
+                    instance.add_property("TopSurface", Enum::from_u32(3));
+                    instance.add_property("BottomSurface", Enum::from_u32(4));
+                }
+                // Neon is 288; it's the closest roblox gets to an emissive material. glow intensity
+                // is brightness rather than opacity, so these stay opaque
+                "BMC_Glow" | "BMC_Neon" => instance.add_property("Material", Enum::from_u32(288)),
                 "BMC_Metallic" => instance.add_property("Material", Enum::from_u32(1088)),
-                "BMC_Hologram" => instance.add_property("Material", Enum::from_u32(1584)),
-                "BMC_Glass" => instance.add_property(
-                    "Transparency",
-                    1.0 - (brick.material_intensity as f32 / 10.0),
-                ),
+                "BMC_Wood" => instance.add_property("Material", Enum::from_u32(512)),
+                "BMC_WoodPlanks" => instance.add_property("Material", Enum::from_u32(528)),
+                "BMC_Concrete" => instance.add_property("Material", Enum::from_u32(816)),
+                "BMC_Granite" => instance.add_property("Material", Enum::from_u32(832)),
+                // holograms fade out as their intensity drops, like glass does
+                "BMC_Hologram" => {
+                    instance.add_property("Material", Enum::from_u32(1584));
+                    transparency = Some(intensity_transparency(brick));
+                }
+                // glass intensity is its opacity, from clear at 0 to solid at 10
+                "BMC_Glass" => transparency = Some(intensity_transparency(brick)),
                 _ => (),
             }
         } else {
-            instance.add_property("Transparency", 1.0f32);
+            transparency = Some(1.0);
+        }
+
+        let clamped = transparency
+            .unwrap_or(0.0)
+            .clamp(options.min_transparency, options.max_transparency);
+        if transparency.is_some() || clamped != 0.0 {
+            instance.add_property("Transparency", clamped);
         }
 
         // collision
-        if !brick.collision.player {
-            instance.add_property("CanCollide", false);
+        match options.collision {
+            CollisionMode::Default if !brick.collision.player => {
+                instance.add_property("CanCollide", false)
+            }
+            CollisionMode::Default => (),
+            CollisionMode::None => instance.add_property("CanCollide", false),
+            CollisionMode::All => instance.add_property("CanCollide", true),
         }
 
         // anchor
-        instance.add_property("Anchored", true);
+        if options.anchored {
+            instance.add_property("Anchored", true);
+        }
 
         // components
-        match brick.components.get("BCD_PointLight") {
-            Some(component) => {
-                let mut light = InstanceBuilder::new("PointLight");
-                light.add_property(
-                    "Brightness",
-                    component_property!(component, "Brightness", UnrealType::Float, &10.0) / 10.0,
-                );
-                light.add_property(
-                    "Range",
-                    component_property!(component, "Range", UnrealType::Float, &100.0) / 10.0,
+        if options.lights && !options.parts_only {
+            if let Some(component) = brick.components.get("BCD_PointLight") {
+                instance.add_child(light("PointLight", component, color_value, options));
+            }
+
+            if let Some(component) = brick.components.get("BCD_SpotLight") {
+                let mut spot = light("SpotLight", component, color_value, options);
+                // roblox spot lights only have the one cone, so use the outer edge of brickadia's
+                spot.add_property(
+                    "Angle",
+                    component_property!(component, "OuterAngle", UnrealType::Float, &90.0)
+                        .min(180.0),
                 );
-                light.add_property(
-                    "Shadows",
-                    *component_property!(component, "bCastShadows", UnrealType::Boolean, &false),
+                instance.add_child(spot);
+            }
+
+            if let Some(component) = brick.components.get("BCD_SurfaceLight") {
+                let mut surface = light("SurfaceLight", component, color_value, options);
+                surface.add_property(
+                    "Angle",
+                    component_property!(component, "Angle", UnrealType::Float, &90.0).min(180.0),
                 );
-                if let UnrealType::Boolean(true) = component["bUseBrickColor"] {
-                    light.add_property("Color", color_value);
-                } else {
-                    let color = component_property!(
-                        component,
-                        "Color",
-                        UnrealType::Color,
-                        &Color {
-                            r: 255,
-                            g: 255,
-                            b: 255,
-                            a: 255
-                        }
-                    );
-
-                    light.add_property(
-                        "Color",
-                        Color3::new(
-                            linear_to_srgb(color.r as f32 / 255.0),
-                            linear_to_srgb(color.g as f32 / 255.0),
-                            linear_to_srgb(color.b as f32 / 255.0),
-                        ),
-                    );
+                if let Some(UnrealType::Byte(face)) = component.get("Face") {
+                    if let Some(face) = normal_id(*face) {
+                        surface.add_property("Face", face);
+                    }
                 }
-                instance.add_child(light);
+                instance.add_child(surface);
             }
-            _ => (),
         }
 
-        self.properties
+        if let Some(component) = brick
+            .components
+            .get("BCD_AudioEmitter")
+            .filter(|_| !options.parts_only)
+        {
+            instance.add_child(sound(component));
+        }
+
+        // clickable bricks. the distance is in brickadia units like positions are, while roblox's
+        // default is 32 studs
+        if let Some(component) = brick
+            .components
+            .get("BCD_Interact")
+            .filter(|_| !options.parts_only)
+        {
+            let distance = match component.get("InteractDistance") {
+                Some(UnrealType::Float(distance)) => distance / options.scale,
+                _ => 32.0,
+            };
+            instance.add_child(
+                InstanceBuilder::new("ClickDetector")
+                    .with_property("MaxActivationDistance", distance),
+            );
+        }
+
+        // sign text, shown on one face of the part
+        if let Some(component) = brick
+            .components
+            .get("BCD_Sign")
+            .filter(|_| !options.parts_only)
+        {
+            instance.add_child(sign(component));
+        }
+
+        // per-brick text, kept in a value so scripts can read it without digging through guis
+        if let Some(component) = ["BCD_Sign", "BCD_Text"]
+            .iter()
+            .find_map(|name| brick.components.get(*name))
+            .filter(|_| !options.parts_only)
+        {
+            let message: &str = component_property!(component, "Message", UnrealType::String, "");
+            instance.add_child(
+                InstanceBuilder::new("StringValue")
+                    .with_name("Text")
+                    .with_property("Value", message),
+            );
+        }
+
+        // original brick data, for scripts in roblox to make use of. roblox attributes have no
+        // integer type, so the direction and rotation are numbers
+        instance.add_property(
+            "Attributes",
+            Attributes::new()
+                .with("BrsOwner", owner_name(save, brick))
+                .with(
+                    "BrsPosition",
+                    Vector3::new(
+                        brick.position.0 as f32,
+                        brick.position.1 as f32,
+                        brick.position.2 as f32,
+                    ),
+                )
+                .with("BrsAsset", asset_name(save, brick).unwrap_or_default())
+                .with("BrsMaterial", material)
+                .with("BrsDirection", brick.direction as u8 as f64)
+                .with("BrsRotation", brick.rotation as u8 as f64),
+        );
+
+        if let Some((mesh_type, scale)) = this.mesh {
+            instance.add_child(
+                InstanceBuilder::new("SpecialMesh")
+                    .with_property("MeshType", Enum::from_u32(mesh_type))
+                    .with_property("Scale", scale),
+            );
+        }
+
+        this.properties
             .into_iter()
             .for_each(|(key, value)| instance.add_property(key, value));
 
@@ -227,6 +618,210 @@ impl PartDef {
     }
 }
 
+/// Transparency for materials whose intensity (0 to 10) is their opacity.
+fn intensity_transparency(brick: &Brick) -> f32 {
+    1.0 - brick.material_intensity.min(10) as f32 / 10.0
+}
+
+/// The name of the player who placed `brick`, or `PUBLIC` for bricks without an owner.
+pub fn owner_name<'a>(save: &'a SaveData, brick: &Brick) -> &'a str {
+    // owner indices are 1-based, 0 being the public owner
+    match brick.owner_index {
+        0 => "PUBLIC",
+        i => save
+            .header2
+            .brick_owners
+            .get(i as usize - 1)
+            .map_or("PUBLIC", |owner| owner.name.as_str()),
+    }
+}
+
+/// Where a brick ends up in roblox, before any converter's own offset.
+fn brick_frame(brick: &Brick, options: &ConvertOptions) -> CoordinateFrame {
+    let mat_comp =
+        ORIENTATION_MAP[((brick.direction as u8) << 2 | (brick.rotation as u8)) as usize];
+
+    CoordinateFrame::new(options.offset[0], options.offset[1], options.offset[2])
+        * options.transform.clone()
+        * CoordinateFrame::from_rotation(
+            brick.position.0 as f32 / options.scale,
+            brick.position.2 as f32 / options.scale,
+            brick.position.1 as f32 / options.scale,
+            mat_comp,
+        )
+}
+
+/// Snaps a part's frame to `ConvertOptions::snap`, if set.
+fn snap(frame: CoordinateFrame, options: &ConvertOptions) -> CoordinateFrame {
+    if options.snap > 0.0 {
+        frame.snap_to_grid(options.snap)
+    } else {
+        frame
+    }
+}
+
+/// A magenta box exactly covering a brick's bounds, for `ConvertOptions::debug_bounds`.
+fn debug_bounds(brick: &Brick, options: &ConvertOptions, size: Vector3) -> InstanceBuilder {
+    let factor = 10.0 / options.scale;
+    InstanceBuilder::new("Part")
+        .with_name("DebugBounds")
+        .with_property(
+            "Size",
+            Vector3::new(size.x * factor, size.y * factor, size.z * factor),
+        )
+        .with_property(
+            "CFrame",
+            CFrame::from(snap(brick_frame(brick, options), options)),
+        )
+        .with_property("Color", Color3::new(1.0, 0.0, 1.0))
+        .with_property("Transparency", 0.8f32)
+        .with_property("CanCollide", false)
+        .with_property("Anchored", options.anchored)
+}
+
+/// Builds a `SurfaceGui` showing a `BCD_Sign`'s message on a `TextLabel` that fills it.
+fn sign(component: &HashMap<String, UnrealType>) -> InstanceBuilder {
+    let message: &str = component_property!(component, "Message", UnrealType::String, "");
+
+    let mut gui = InstanceBuilder::new("SurfaceGui");
+    if let Some(UnrealType::Byte(face)) = component.get("Face") {
+        if let Some(face) = normal_id(*face) {
+            gui.add_property("Face", face);
+        }
+    }
+
+    gui.with_child(
+        InstanceBuilder::new("TextLabel")
+            .with_property("Text", message)
+            .with_property("Size", UDim2::new(UDim::new(1.0, 0), UDim::new(1.0, 0))),
+    )
+}
+
+/// Builds a `Sound` from a `BCD_AudioEmitter`. Bare numeric asset ids are taken to be roblox
+/// assets.
+fn sound(component: &HashMap<String, UnrealType>) -> InstanceBuilder {
+    let asset: &str = component_property!(component, "AssetId", UnrealType::String, "");
+    let sound_id = if !asset.is_empty() && asset.bytes().all(|b| b.is_ascii_digit()) {
+        format!("rbxassetid://{asset}")
+    } else {
+        asset.to_string()
+    };
+
+    InstanceBuilder::new("Sound")
+        .with_property("SoundId", Content::from(sound_id))
+        .with_property(
+            "Volume",
+            *component_property!(component, "Volume", UnrealType::Float, &0.5),
+        )
+}
+
+/// Builds a light with the properties shared by all of brickadia's light components. The range
+/// is in brickadia units, so it's scaled like positions are.
+fn light(
+    class: &str,
+    component: &HashMap<String, UnrealType>,
+    brick_color: Color3,
+    options: &ConvertOptions,
+) -> InstanceBuilder {
+    let mut light = InstanceBuilder::new(class);
+    light.add_property(
+        "Brightness",
+        component_property!(component, "Brightness", UnrealType::Float, &10.0) / 10.0,
+    );
+    light.add_property(
+        "Range",
+        component_property!(component, "Range", UnrealType::Float, &100.0) / options.scale,
+    );
+    light.add_property(
+        "Shadows",
+        *component_property!(component, "bCastShadows", UnrealType::Boolean, &false),
+    );
+
+    if let Some(UnrealType::Boolean(true)) = component.get("bUseBrickColor") {
+        light.add_property("Color", brick_color);
+    } else {
+        let color = component_property!(
+            component,
+            "Color",
+            UnrealType::Color,
+            &Color {
+                r: 255,
+                g: 255,
+                b: 255,
+                a: 255
+            }
+        );
+
+        light.add_property(
+            "Color",
+            Color3::new(
+                linear_to_srgb(color.r as f32 / 255.0),
+                linear_to_srgb(color.g as f32 / 255.0),
+                linear_to_srgb(color.b as f32 / 255.0),
+            ),
+        );
+    }
+
+    light
+}
+
+/// Maps a brickadia face (in `Direction` order: +X, -X, +Y, -Y, +Z, -Z) to a roblox `NormalId`
+/// on the part. Brickadia's Y and Z axes are swapped in roblox, and bricks in their default
+/// orientation face roblox's -X and -Z (see `ORIENTATION_MAP`), so +X is the part's left and +Y
+/// its front. Unknown faces are warned about and left unset.
+fn normal_id(face: u8) -> Option<Enum> {
+    let normal = match face {
+        0 => 3, // Left
+        1 => 0, // Right
+        2 => 5, // Front
+        3 => 2, // Back
+        4 => 1, // Top
+        5 => 4, // Bottom
+        _ => {
+            eprintln!("Warning: unknown face {face}, leaving it unset");
+            return None;
+        }
+    };
+    Some(Enum::from_u32(normal))
+}
+
+/// The color `brick` is painted, as stored in the save (linear). Bricks whose palette index is
+/// out of range are white.
+pub fn brick_color<'a>(save: &'a SaveData, brick: &'a Brick) -> &'a Color {
+    static WHITE: Color = Color {
+        r: 255,
+        g: 255,
+        b: 255,
+        a: 255,
+    };
+
+    match &brick.color {
+        BrickColor::Index(idx) => save.header2.colors.get(*idx as usize).unwrap_or(&WHITE),
+        BrickColor::Unique(c) => c,
+    }
+}
+
+/// The name of `brick`'s asset, or `None` if its asset index is out of range.
+pub fn asset_name<'a>(save: &'a SaveData, brick: &Brick) -> Option<&'a str> {
+    save.header2
+        .brick_assets
+        .get(brick.asset_name_index as usize)
+        .map(|a| a.as_str())
+}
+
+/// The name of `brick`'s material. Bricks whose material index is out of range are plastic.
+pub fn material_name<'a>(save: &'a SaveData, brick: &Brick) -> &'a str {
+    save.header2
+        .materials
+        .get(brick.material_index as usize)
+        .map_or("BMC_Plastic", |m| m.as_str())
+}
+
+/// A save color as the 0-255 sRGB roblox ends up displaying.
+pub(crate) fn srgb_bytes(color: &Color) -> [u8; 3] {
+    [color.r, color.g, color.b].map(|c| (linear_to_srgb(c as f32 / 255.0) * 255.0).round() as u8)
+}
+
 fn linear_to_srgb(c: f32) -> f32 {
     if c > 0.0031308 {
         1.055 * c.powf(1.0 / 2.4) - 0.055
@@ -235,93 +830,247 @@ fn linear_to_srgb(c: f32) -> f32 {
     }
 }
 
-pub fn convert_brick(brick: &Brick, save: &SaveData) -> Option<Vec<InstanceBuilder>> {
-    let asset = save.header2.brick_assets[brick.asset_name_index as usize].as_str();
+/// The inverse of `linear_to_srgb`.
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c > 0.04045 {
+        ((c + 0.055) / 1.055).powf(2.4)
+    } else {
+        c / 12.92
+    }
+}
+
+/// An upright octagonal prism, `radius` from its center to each flat side.
+///
+/// It's made of eight spokes running from the center out to each side. Each is exactly as wide as
+/// a side, so together they cover the octagon without leaving gaps at the vertices.
+fn octagonal_cylinder_parts(radius: f32, height: f32) -> Vec<PartDef> {
+    let side = 2.0 * radius * (PI / 8.0).tan();
+    (0..8)
+        .map(|i| {
+            PartDef::new("Part")
+                .size(side, height, radius)
+                .cf(CoordinateFrame::ry(i as f32 * PI / 4.0))
+                .offset(0.0, 0.0, radius / 2.0)
+        })
+        .collect()
+}
+
+/// Every asset [`convert_brick`] has a converter for.
+pub const ASSETS: &[&str] = &[
+    "PB_DefaultBrick",
+    "PB_DefaultStudded",
+    "PB_DefaultBrickWithStuds",
+    "PB_DefaultTile",
+    "PB_DefaultRamp",
+    "PB_DefaultRampInverted",
+    "PB_DefaultRampCorner",
+    "PB_DefaultRampCornerInverted",
+    "PB_DefaultRampCrest",
+    "PB_DefaultWedge",
+    "PB_DefaultSideWedge",
+    "PB_DefaultSideWedgeTile",
+    "PB_DefaultMicroBrick",
+    "PB_DefaultMicroWedge",
+    "PB_DefaultMicroBrickWedge",
+    "PB_DefaultMicroWedgeInnerCorner",
+    "PB_DefaultMicroWedgeOuterCorner",
+    "PB_DefaultRound",
+    "PB_DefaultBrickRound",
+    "B_2x2_Round",
+    "B_2x2F_Round",
+    "B_1x1_Round",
+    "B_1x1_Cone",
+    "B_1x1F_Round",
+    "B_1x1_Octo",
+    "B_2x2_Octo",
+];
+
+/// Converts a brick into roblox instances. `None` means there is no converter for the brick's
+/// asset, and an empty list that the brick is procedural but has no size.
+pub fn convert_brick(
+    brick: &Brick,
+    save: &SaveData,
+    options: &ConvertOptions,
+) -> Option<Vec<InstanceBuilder>> {
+    // out of range asset indices are warned about once per save, in `convert_model`
+    let asset = asset_name(save, brick)?;
 
     let size = match brick.size {
         Size::Empty => (0.0, 0.0, 0.0),
         Size::Procedural(x, y, z) => (x as f32 / 5.0, y as f32 / 5.0, z as f32 / 5.0),
     };
 
-    return Some(match asset {
-        "PB_DefaultBrick" => vec![PartDef::default()
+    // every procedural brick is sized by its converter, so one with no size would come out as
+    // degenerate zero size parts. those are left out rather than converted
+    if asset.starts_with("PB_") && size == (0.0, 0.0, 0.0) {
+        return Some(vec![]);
+    }
+
+    let mut instances = match asset {
+        "PB_DefaultBrick" => {
+            let mut part = PartDef::default().size(size.0, size.2, size.1);
+
+            // thin bricks are usually the shell of a build rather than fill, tag them so that
+            // game logic can tell the two apart
+            let threshold = options.hollow_detection_threshold;
+            if threshold > 0.0 && size.0.min(size.1).min(size.2) <= threshold {
+                part = part.property("Tags", Tags::from(vec!["BRS_WallPanel".to_string()]));
+            }
+
+            vec![part.to_instance(save, brick, options)]
+        }
+
+        // surfaces are `SurfaceType` enums: 0 smooth, 3 studs, 4 inlet. roblox parts default to
+        // studs on top and inlets underneath, and smooth on the sides, which `PB_DefaultBrick`
+        // relies on. this sets the studs explicitly anyway, while smooth shapes like
+        // `PB_DefaultTile` and `PB_DefaultMicroBrick` have to set their surfaces to 0
+        "PB_DefaultStudded" => vec![PartDef::default()
             .size(size.0, size.2, size.1)
-            .to_instance(&save, brick)],
+            .property("TopSurface", Enum::from_u32(3))
+            .to_instance(save, brick, options)],
+
+        // studs on top and inlets underneath, the same as a classic roblox brick
+        "PB_DefaultBrickWithStuds" => vec![PartDef::default()
+            .size(size.0, size.2, size.1)
+            .property("TopSurface", Enum::from_u32(3))
+            .property("BottomSurface", Enum::from_u32(4))
+            .to_instance(save, brick, options)],
 
         "PB_DefaultTile" => vec![PartDef::default()
             .size(size.0, size.2, size.1)
             .property("TopSurface", Enum::from_u32(0))
-            .to_instance(&save, brick)],
+            .to_instance(save, brick, options)],
 
         "PB_DefaultRamp" => vec![
             PartDef::new("Part")
                 .size(1.0, size.2, size.1)
                 .offset(size.0 / 2.0 - 0.5, 0.0, 0.0)
-                .to_instance(&save, brick),
+                .to_instance(save, brick, options),
             PartDef::new("WedgePart")
                 .size(size.1, size.2 - 0.2, size.0 - 1.0)
                 .offset(-0.5, 0.1, 0.0)
                 .cf(CoordinateFrame::ry(PI * 0.5))
-                .to_instance(&save, brick),
+                .to_instance(save, brick, options),
             PartDef::new("Part")
                 .size(size.0 - 1.0, 0.2, size.1)
                 .offset(-0.5, -(size.2 / 2.0) + 0.1, 0.0)
-                .to_instance(&save, brick),
+                .to_instance(save, brick, options),
         ],
 
         "PB_DefaultRampInverted" => vec![
             PartDef::new("Part")
                 .size(1.0, size.2, size.1)
                 .offset(size.0 / 2.0 - 0.5, 0.0, 0.0)
-                .to_instance(&save, brick),
+                .to_instance(save, brick, options),
             PartDef::new("WedgePart")
                 .size(size.1, size.2 - 0.2, size.0 - 1.0)
                 .offset(-0.5, -0.1, 0.0)
                 .cf(CoordinateFrame::rx(PI))
                 .cf(CoordinateFrame::ry(PI * 0.5))
-                .to_instance(&save, brick),
+                .to_instance(save, brick, options),
             PartDef::new("Part")
                 .size(size.0 - 1.0, 0.2, size.1)
                 .offset(-0.5, (size.2 / 2.0) - 0.1, 0.0)
-                .to_instance(&save, brick),
+                .to_instance(save, brick, options),
+        ],
+
+        // the corner column with a ramp running down each edge away from it. the interior, where
+        // the two slopes meet, is a corner wedge with its peak turned towards the column
+        "PB_DefaultRampCorner" => vec![
+            PartDef::new("Part")
+                .size(1.0, size.2, 1.0)
+                .offset(size.0 / 2.0 - 0.5, 0.0, size.1 / 2.0 - 0.5)
+                .to_instance(save, brick, options),
+            PartDef::new("WedgePart")
+                .size(1.0, size.2 - 0.2, size.0 - 1.0)
+                .offset(-0.5, 0.1, size.1 / 2.0 - 0.5)
+                .cf(CoordinateFrame::ry(PI * 0.5))
+                .to_instance(save, brick, options),
+            PartDef::new("WedgePart")
+                .size(1.0, size.2 - 0.2, size.1 - 1.0)
+                .offset(size.0 / 2.0 - 0.5, 0.1, -0.5)
+                .to_instance(save, brick, options),
+            PartDef::new("CornerWedgePart")
+                .size(size.1 - 1.0, size.2 - 0.2, size.0 - 1.0)
+                .offset(-0.5, 0.1, -0.5)
+                .cf(CoordinateFrame::ry(-PI * 0.5))
+                .to_instance(save, brick, options),
+            PartDef::new("Part")
+                .size(size.0 - 1.0, 0.2, size.1)
+                .offset(-0.5, -(size.2 / 2.0) + 0.1, 0.0)
+                .to_instance(save, brick, options),
+            PartDef::new("Part")
+                .size(1.0, 0.2, size.1 - 1.0)
+                .offset(size.0 / 2.0 - 0.5, -(size.2 / 2.0) + 0.1, -0.5)
+                .to_instance(save, brick, options),
         ],
 
+        "PB_DefaultRampCornerInverted" => vec![
+            PartDef::new("Part")
+                .size(1.0, size.2, 1.0)
+                .offset(size.0 / 2.0 - 0.5, 0.0, size.1 / 2.0 - 0.5)
+                .to_instance(save, brick, options),
+            PartDef::new("WedgePart")
+                .size(1.0, size.2 - 0.2, size.0 - 1.0)
+                .offset(-0.5, -0.1, size.1 / 2.0 - 0.5)
+                .cf(CoordinateFrame::rx(PI))
+                .cf(CoordinateFrame::ry(PI * 0.5))
+                .to_instance(save, brick, options),
+            PartDef::new("WedgePart")
+                .size(1.0, size.2 - 0.2, size.1 - 1.0)
+                .offset(size.0 / 2.0 - 0.5, -0.1, -0.5)
+                .cf(CoordinateFrame::rz(PI))
+                .to_instance(save, brick, options),
+            PartDef::new("CornerWedgePart")
+                .size(size.0 - 1.0, size.2 - 0.2, size.1 - 1.0)
+                .offset(-0.5, -0.1, -0.5)
+                .cf(CoordinateFrame::rx(PI))
+                .to_instance(save, brick, options),
+            PartDef::new("Part")
+                .size(size.0 - 1.0, 0.2, size.1)
+                .offset(-0.5, (size.2 / 2.0) - 0.1, 0.0)
+                .to_instance(save, brick, options),
+            PartDef::new("Part")
+                .size(1.0, 0.2, size.1 - 1.0)
+                .offset(size.0 / 2.0 - 0.5, (size.2 / 2.0) - 0.1, -0.5)
+                .to_instance(save, brick, options),
+        ],
+
+        // the crest joins two ramps back to back, and is flat along its full size
+        "PB_DefaultRampCrest" => vec![PartDef::new("Part")
+            .size(size.0, size.2, size.1)
+            .to_instance(save, brick, options)],
+
         "PB_DefaultWedge" => vec![
             PartDef::new("WedgePart")
                 .size(size.1, size.2 - 0.2, size.0)
                 .offset(0.0, 0.1, 0.0)
                 .cf(CoordinateFrame::ry(PI * 0.5))
-                .to_instance(&save, brick),
+                .to_instance(save, brick, options),
             PartDef::new("Part")
                 .size(size.1, 0.2, size.0)
                 .offset(0.0, -(size.2 / 2.0) + 0.1, 0.0)
                 .cf(CoordinateFrame::ry(PI * 0.5))
-                .to_instance(&save, brick),
+                .to_instance(save, brick, options),
         ],
 
         "PB_DefaultSideWedge" => vec![PartDef::new("WedgePart")
             .size(size.2, size.0, size.1)
             .cf(CoordinateFrame::rz(PI * 0.5))
-            .property("TopSurface", Enum::from_u32(0))
-            .property("BottomSurface", Enum::from_u32(0))
-            .property("LeftSurface", Enum::from_u32(4))
-            .property("RightSurface", Enum::from_u32(3))
-            .to_instance(&save, brick)],
+            .with_surface_normals(0, 0, 4, 3, 0, 0)
+            .to_instance(save, brick, options)],
 
         "PB_DefaultSideWedgeTile" => vec![PartDef::new("WedgePart")
             .size(size.2, size.0, size.1)
             .cf(CoordinateFrame::rz(PI * 0.5))
-            .property("TopSurface", Enum::from_u32(0))
-            .property("BottomSurface", Enum::from_u32(0))
-            .property("LeftSurface", Enum::from_u32(4))
-            .property("RightSurface", Enum::from_u32(0))
-            .to_instance(&save, brick)],
+            .with_surface_normals(0, 0, 4, 0, 0, 0)
+            .to_instance(save, brick, options)],
 
         "PB_DefaultMicroBrick" => vec![PartDef::new("Part")
             .size(size.0, size.2, size.1)
             .property("TopSurface", Enum::from_u32(0))
             .property("BottomSurface", Enum::from_u32(0))
-            .to_instance(&save, brick)],
+            .to_instance(save, brick, options)],
 
         "PB_DefaultMicroWedge" => vec![PartDef::new("WedgePart")
             .size(size.2, size.1, size.0)
@@ -329,60 +1078,188 @@ pub fn convert_brick(brick: &Brick, save: &SaveData) -> Option<Vec<InstanceBuild
             .cf(CoordinateFrame::rx(-PI * 0.5))
             .cf(CoordinateFrame::ry(PI))
             .property("BottomSurface", Enum::from_u32(0))
-            .to_instance(&save, brick)],
+            .to_instance(save, brick, options)],
+
+        // the micro scale counterpart of PB_DefaultWedge: the same single slope, turned the same
+        // way, but running the full height since micro bricks have no base plate
+        "PB_DefaultMicroBrickWedge" => vec![PartDef::new("WedgePart")
+            .size(size.1, size.2, size.0)
+            .cf(CoordinateFrame::ry(PI * 0.5))
+            .property("BottomSurface", Enum::from_u32(0))
+            .to_instance(save, brick, options)],
 
         "PB_DefaultMicroWedgeInnerCorner" => vec![
             PartDef::new("WedgePart")
                 .size(size.0, size.2, size.1)
                 .cf(CoordinateFrame::ry(PI * 0.5))
                 .property("BottomSurface", Enum::from_u32(0))
-                .to_instance(&save, brick),
+                .to_instance(save, brick, options),
             PartDef::new("WedgePart")
                 .size(size.1, size.2, size.0)
                 .property("BottomSurface", Enum::from_u32(0))
-                .to_instance(&save, brick),
+                .to_instance(save, brick, options),
         ],
 
+        // where the inner corner is the union of the two wedges, the outer corner is their
+        // intersection, which is exactly a corner wedge
+        "PB_DefaultMicroWedgeOuterCorner" => vec![PartDef::new("CornerWedgePart")
+            .size(size.1, size.2, size.0)
+            .property("BottomSurface", Enum::from_u32(0))
+            .to_instance(save, brick, options)],
+
+        // a SpecialMesh cylinder (`MeshType` 4) runs along the part's X axis, so the part is
+        // turned upright and its X is the brick's height
+        "PB_DefaultRound" => vec![PartDef::new("Part")
+            .size(size.2, size.0, size.1)
+            .cf(CoordinateFrame::rz(PI * 0.5))
+            .mesh(4, Vector3::new(1.0, 1.0, 1.0))
+            .to_instance(save, brick, options)],
+
+        // roblox balls (`Shape` 0) are always as wide as they are tall, so use the smallest
+        // dimension to keep the ball inside the brick
+        "PB_DefaultBrickRound" => {
+            let diameter = size.0.min(size.1).min(size.2);
+            vec![PartDef::new("Part")
+                .size(diameter, diameter, diameter)
+                .property("Shape", Enum::from_u32(0))
+                .property("TopSurface", Enum::from_u32(0))
+                .property("BottomSurface", Enum::from_u32(0))
+                .to_instance(save, brick, options)]
+        }
+
         "B_2x2_Round" => vec![PartDef::new("Part")
             .size(1.2, 2.0, 2.0)
             .cf(CoordinateFrame::rz(PI * 0.5))
             .property("Shape", Enum::from_u32(2))
-            .property("TopSurface", Enum::from_u32(0))
-            .property("BottomSurface", Enum::from_u32(0))
-            .property("LeftSurface", Enum::from_u32(4))
-            .property("RightSurface", Enum::from_u32(3))
-            .to_instance(&save, brick)],
+            .with_surface_normals(0, 0, 4, 3, 0, 0)
+            .to_instance(save, brick, options)],
 
         "B_2x2F_Round" => vec![PartDef::new("Part")
             .size(0.4, 2.0, 2.0)
             .cf(CoordinateFrame::rz(PI * 0.5))
             .property("Shape", Enum::from_u32(2))
-            .property("TopSurface", Enum::from_u32(0))
-            .property("BottomSurface", Enum::from_u32(0))
-            .property("LeftSurface", Enum::from_u32(4))
-            .property("RightSurface", Enum::from_u32(3))
-            .to_instance(&save, brick)],
+            .with_surface_normals(0, 0, 4, 3, 0, 0)
+            .to_instance(save, brick, options)],
 
-        "B_1x1_Round" | "B_1x1_Cone" => vec![PartDef::new("Part")
+        "B_1x1_Round" => vec![PartDef::new("Part")
             .size(1.2, 1.0, 1.0)
             .cf(CoordinateFrame::rz(PI * 0.5))
             .property("Shape", Enum::from_u32(2))
-            .property("TopSurface", Enum::from_u32(0))
-            .property("BottomSurface", Enum::from_u32(0))
-            .property("LeftSurface", Enum::from_u32(4))
-            .property("RightSurface", Enum::from_u32(3))
-            .to_instance(&save, brick)],
+            .with_surface_normals(0, 0, 4, 3, 0, 0)
+            .to_instance(save, brick, options)],
+
+        // a square pyramid: one corner wedge per quadrant, each with its tall edge (at its own
+        // +X, -Z corner) at the center
+        "B_1x1_Cone" => (0..4)
+            .map(|i| {
+                PartDef::new("CornerWedgePart")
+                    .size(0.5, 1.2, 0.5)
+                    .cf(CoordinateFrame::ry(i as f32 * PI / 2.0))
+                    .offset(-0.25, 0.0, 0.25)
+                    .to_instance(save, brick, options)
+            })
+            .collect(),
 
         "B_1x1F_Round" => vec![PartDef::new("Part")
             .size(1.2, 1.0, 1.0)
             .cf(CoordinateFrame::rz(PI * 0.5))
             .property("Shape", Enum::from_u32(2))
-            .property("TopSurface", Enum::from_u32(0))
-            .property("BottomSurface", Enum::from_u32(0))
-            .property("LeftSurface", Enum::from_u32(4))
-            .property("RightSurface", Enum::from_u32(3))
-            .to_instance(&save, brick)],
+            .with_surface_normals(0, 0, 4, 3, 0, 0)
+            .to_instance(save, brick, options)],
+
+        "B_1x1_Octo" => octagonal_cylinder_parts(0.5, 1.2)
+            .into_iter()
+            .map(|part| part.to_instance(save, brick, options))
+            .collect(),
+
+        "B_2x2_Octo" => octagonal_cylinder_parts(1.0, 1.2)
+            .into_iter()
+            .map(|part| part.to_instance(save, brick, options))
+            .collect(),
 
         _ => return None,
-    });
+    };
+
+    // vehicles are driven from a seat. there's no telling which brick of a vehicle is meant to be
+    // the seat, so the best that can be done is making the brick with the component one
+    if let (Some(component), Some(seat)) =
+        (brick.components.get("BCD_Vehicle"), instances.first_mut())
+    {
+        seat.set_class("VehicleSeat");
+        // brickadia speeds are in units per second, roblox's default is 25 studs per second
+        let max_speed = match component.get("Speed") {
+            Some(UnrealType::Float(speed)) => speed / options.scale,
+            _ => 25.0,
+        };
+        seat.add_property("MaxSpeed", max_speed);
+    }
+
+    if options.debug_bounds {
+        if let Size::Procedural(..) = brick.size {
+            instances.push(debug_bounds(
+                brick,
+                options,
+                Vector3::new(size.0, size.2, size.1),
+            ));
+        }
+    }
+
+    Some(instances)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normal_id_matches_default_orientation() {
+        // the default orientation, brickadia direction ZPositive with no rotation
+        let m = ORIENTATION_MAP[(4 << 2) as usize];
+        let brickadia = [
+            [1.0, 0.0, 0.0],
+            [-1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, -1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [0.0, 0.0, -1.0],
+        ];
+        // roblox's NormalId order: Right, Top, Back, Left, Bottom, Front
+        let normals = [
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [-1.0, 0.0, 0.0],
+            [0.0, -1.0, 0.0],
+            [0.0, 0.0, -1.0],
+        ];
+
+        for (face, [x, y, z]) in brickadia.into_iter().enumerate() {
+            // brickadia's axes in roblox, then into the part's local space, which is the
+            // transpose of its rotation
+            let world = [x, z, y];
+            let local: [f32; 3] = [0, 1, 2].map(|j| (0..3).map(|i| m[i * 3 + j] * world[i]).sum());
+            let expected = normals.iter().position(|n| *n == local).unwrap() as u32;
+            assert_eq!(
+                normal_id(face as u8),
+                Some(Enum::from_u32(expected)),
+                "face {face}"
+            );
+        }
+        assert_eq!(normal_id(6), None);
+    }
+
+    #[test]
+    fn scale_size_scales_every_axis() {
+        let part = PartDef::new("Part").size(2.0, 4.0, 2.0).scale_size(0.5);
+        assert_eq!(part.size, Vector3::new(1.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn srgb_round_trips() {
+        for i in 0..=1000 {
+            let x = i as f32 / 1000.0;
+            let y = srgb_to_linear(linear_to_srgb(x));
+            assert!((x - y).abs() < 1e-5, "{x} came back as {y}");
+        }
+    }
 }
@@ -0,0 +1,91 @@
+use std::{error::Error, fmt, fs::File, io::BufReader, path::Path};
+
+use rbx_dom_weak::types::{CFrame, Variant};
+
+pub struct ValidationReport {
+    /// Number of instances in the file, including the root model.
+    pub instances: usize,
+    /// Number of direct children of the root model that aren't the attribution script or the
+    /// metadata folder, i.e. one per converted brick.
+    pub bricks: usize,
+}
+
+#[derive(Debug)]
+pub enum ValidationError {
+    Io(std::io::Error),
+    Decode(rbx_binary::DecodeError),
+    NoRoot,
+    RootNotModel { name: String, class: String },
+    NonFiniteCFrame { name: String, class: String },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to open model: {e}"),
+            Self::Decode(e) => write!(f, "failed to decode model: {e}"),
+            Self::NoRoot => write!(f, "model has no root instance"),
+            Self::RootNotModel { name, class } => {
+                write!(f, "root instance {name} is a {class}, expected a Model")
+            }
+            Self::NonFiniteCFrame { name, class } => {
+                write!(f, "{class} {name} has a non-finite CFrame")
+            }
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+/// Reads a written `.rbxm` back in and sanity checks it.
+pub fn validate_rbxm(path: &Path) -> Result<ValidationReport, ValidationError> {
+    let file = File::open(path).map_err(ValidationError::Io)?;
+    let dom = rbx_binary::from_reader(BufReader::new(file)).map_err(ValidationError::Decode)?;
+
+    let root_ref = *dom
+        .root()
+        .children()
+        .first()
+        .ok_or(ValidationError::NoRoot)?;
+    let root = dom.get_by_ref(root_ref).unwrap();
+    if root.class != "Model" {
+        return Err(ValidationError::RootNotModel {
+            name: root.name.clone(),
+            class: root.class.clone(),
+        });
+    }
+
+    let bricks = root
+        .children()
+        .iter()
+        .filter_map(|r| dom.get_by_ref(*r))
+        .filter(|i| i.class != "Script" && i.class != "Folder")
+        .count();
+
+    let mut instances = 0;
+    for instance in dom.descendants_of(root_ref) {
+        instances += 1;
+
+        if let Some(Variant::CFrame(cf)) = instance.properties.get("CFrame") {
+            if !is_finite(cf) {
+                return Err(ValidationError::NonFiniteCFrame {
+                    name: instance.name.clone(),
+                    class: instance.class.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(ValidationReport { instances, bricks })
+}
+
+fn is_finite(cf: &CFrame) -> bool {
+    [
+        cf.position,
+        cf.orientation.x,
+        cf.orientation.y,
+        cf.orientation.z,
+    ]
+    .iter()
+    .all(|v| v.x.is_finite() && v.y.is_finite() && v.z.is_finite())
+}
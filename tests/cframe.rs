@@ -0,0 +1,256 @@
+use std::f32::consts::PI;
+
+use brs2rbxl::{part::ORIENTATION_MAP, CoordinateFrame};
+use rbx_dom_weak::types::{CFrame, Vector3};
+
+const EPSILON: f32 = 1e-6;
+
+#[test]
+fn orientation_map_round_trips() {
+    for (i, mat) in ORIENTATION_MAP.iter().enumerate() {
+        let rotation = CoordinateFrame::from_rotation(0.0, 0.0, 0.0, *mat).rotation_matrix();
+        let rows = [rotation.x, rotation.y, rotation.z];
+        for (r, row) in rows.iter().enumerate() {
+            for (c, value) in [row.x, row.y, row.z].into_iter().enumerate() {
+                assert!(
+                    (value - mat[r * 3 + c]).abs() < EPSILON,
+                    "entry {i} differs at ({r}, {c}): {value} != {}",
+                    mat[r * 3 + c]
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn orientation_map_is_not_degenerate() {
+    for (i, mat) in ORIENTATION_MAP.iter().enumerate() {
+        let cframe = CFrame::from(CoordinateFrame::from_rotation(0.0, 0.0, 0.0, *mat));
+        let (x, y, z) = (
+            cframe.orientation.x,
+            cframe.orientation.y,
+            cframe.orientation.z,
+        );
+        let det = x.x * (y.y * z.z - y.z * z.y) - x.y * (y.x * z.z - y.z * z.x)
+            + x.z * (y.x * z.y - y.y * z.x);
+        assert!(det.abs() > EPSILON, "entry {i} has a zero determinant");
+    }
+}
+
+#[test]
+fn from_rotation_is_row_major() {
+    let rot = [0.0, -1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+    let rows = [
+        [0.0, -1.0, 0.0, 1.0],
+        [1.0, 0.0, 0.0, 2.0],
+        [0.0, 0.0, 1.0, 3.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+    let mut columns = [[0.0; 4]; 4];
+    for (i, row) in rows.iter().enumerate() {
+        for (j, value) in row.iter().enumerate() {
+            columns[j][i] = *value;
+        }
+    }
+
+    let expected = CoordinateFrame::from_rotation(1.0, 2.0, 3.0, rot).decompose();
+    for frame in [
+        CoordinateFrame::from_matrix_row_major(rows),
+        CoordinateFrame::from_matrix_col_major(columns),
+    ] {
+        let (translation, rotation, _) = frame.decompose();
+        assert_eq!(translation, expected.0);
+        assert_eq!(rotation, expected.1);
+    }
+}
+
+/// The rotation of `frame` as 9 row-major values, like `ORIENTATION_MAP`'s.
+fn rotation(frame: &CoordinateFrame) -> [f32; 9] {
+    let m = frame.rotation_matrix();
+    [
+        m.x.x, m.x.y, m.x.z, m.y.x, m.y.y, m.y.z, m.z.x, m.z.y, m.z.z,
+    ]
+}
+
+fn assert_rotation_eq(frame: &CoordinateFrame, expected: &[f32; 9], what: &str) {
+    let actual = rotation(frame);
+    assert!(
+        actual
+            .iter()
+            .zip(expected)
+            .all(|(a, b)| (a - b).abs() < 1e-5),
+        "{what}: {actual:?} != {expected:?}"
+    );
+}
+
+#[test]
+fn intrinsic_euler_angles_match_orientation_map() {
+    let mut differs_from_extrinsic = false;
+    for (i, mat) in ORIENTATION_MAP.iter().enumerate() {
+        // intrinsic xyz angles of `mat`, which is rx * ry * rz. when ry is a quarter turn, x and z
+        // rotate about the same axis, so all of it goes in rx
+        let ry = mat[2].clamp(-1.0, 1.0).asin();
+        let (rx, rz) = if mat[2].abs() > 1.0 - 1e-6 {
+            (mat[7].atan2(mat[4]), 0.0)
+        } else {
+            ((-mat[5]).atan2(mat[8]), (-mat[1]).atan2(mat[0]))
+        };
+
+        assert_rotation_eq(
+            &CoordinateFrame::from_euler_angles_intrinsic(rx, ry, rz),
+            mat,
+            &format!("entry {i}"),
+        );
+        differs_from_extrinsic |= rotation(&CoordinateFrame::angles(rx, ry, rz))
+            .iter()
+            .zip(mat)
+            .any(|(a, b)| (a - b).abs() > 1e-5);
+    }
+    assert!(
+        differs_from_extrinsic,
+        "angles should disagree with the intrinsic rotation"
+    );
+}
+
+fn position(frame: &CoordinateFrame) -> [f32; 3] {
+    let p = frame.position();
+    [p.x, p.y, p.z]
+}
+
+fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+    assert!(
+        actual
+            .iter()
+            .zip(expected)
+            .all(|(a, b)| (a - b).abs() < 1e-5),
+        "{actual:?} != {expected:?}"
+    );
+}
+
+#[test]
+fn mul_only_translates_points() {
+    let frame = CoordinateFrame::new(1.0, 2.0, 3.0) * CoordinateFrame::ry(PI / 2.0);
+    let point = frame.mul(&[1.0, 0.0, 0.0], true);
+    let direction = frame.mul(&[1.0, 0.0, 0.0], false);
+
+    assert_ne!(point, direction);
+    assert_close(point, [1.0, 2.0, 2.0]);
+    assert_close(direction, [0.0, 0.0, -1.0]);
+}
+
+#[test]
+fn inverse_undoes_the_frame() {
+    let frame = CoordinateFrame::new(4.0, -2.0, 7.5)
+        * CoordinateFrame::from_euler_angles_xyz(0.3, -1.2, 2.0);
+    assert!((frame.clone() * frame.inverse()).is_identity());
+    assert!((frame.inverse() * frame).is_identity());
+}
+
+#[test]
+fn quaternions_round_trip_orientation_map() {
+    for (i, mat) in ORIENTATION_MAP.iter().enumerate() {
+        // to_quaternion gives (w, x, y, z), from_quaternion takes (x, y, z, w)
+        let (w, x, y, z) = CoordinateFrame::from_rotation(0.0, 0.0, 0.0, *mat).to_quaternion();
+        assert!(
+            ((w * w + x * x + y * y + z * z) - 1.0).abs() < 1e-5,
+            "entry {i} isn't a unit quaternion"
+        );
+        assert_rotation_eq(
+            &CoordinateFrame::from_quaternion(x, y, z, w, 0.0, 0.0, 0.0),
+            mat,
+            &format!("entry {i}"),
+        );
+    }
+}
+
+#[test]
+fn lerp_interpolates_translation_and_rotation() {
+    let a = CoordinateFrame::new(0.0, 0.0, 0.0);
+    let b = CoordinateFrame::new(2.0, 4.0, -6.0) * CoordinateFrame::ry(PI / 2.0);
+
+    let start = a.lerp(&b, 0.0);
+    assert_close(position(&start), [0.0, 0.0, 0.0]);
+    assert_rotation_eq(&start, &rotation(&a), "t = 0");
+
+    let middle = a.lerp(&b, 0.5);
+    assert_close(position(&middle), [1.0, 2.0, -3.0]);
+    assert_rotation_eq(
+        &middle,
+        &rotation(&CoordinateFrame::ry(PI / 4.0)),
+        "t = 0.5",
+    );
+
+    let end = a.lerp(&b, 1.0);
+    assert_close(position(&end), [2.0, 4.0, -6.0]);
+    assert_rotation_eq(&end, &rotation(&b), "t = 1");
+}
+
+#[test]
+fn axis_angle_matches_axis_rotations() {
+    for angle in [PI / 2.0, -PI / 3.0, 2.5] {
+        let cases = [
+            (
+                CoordinateFrame::from_axis_angle(1.0, 0.0, 0.0, angle),
+                CoordinateFrame::rx(angle),
+            ),
+            (
+                CoordinateFrame::from_axis_angle(0.0, 1.0, 0.0, angle),
+                CoordinateFrame::ry(angle),
+            ),
+            (
+                CoordinateFrame::from_axis_angle(0.0, 0.0, 3.0, angle),
+                CoordinateFrame::rz(angle),
+            ),
+        ];
+        for (i, (frame, expected)) in cases.iter().enumerate() {
+            assert_rotation_eq(
+                frame,
+                &rotation(expected),
+                &format!("axis {i}, angle {angle}"),
+            );
+        }
+    }
+
+    assert!(CoordinateFrame::from_axis_angle(0.0, 0.0, 0.0, PI).is_identity());
+}
+
+#[test]
+fn look_at_faces_target() {
+    let cases = [
+        ((1.0, 2.0, 3.0), (4.0, -2.0, 3.0), (0.0, 1.0, 0.0)),
+        ((0.0, 0.0, 0.0), (0.0, 5.0, 0.0), (0.0, 1.0, 0.0)),
+        ((0.0, 0.0, 0.0), (0.0, -5.0, 0.0), (0.0, 1.0, 0.0)),
+        ((2.0, 0.0, 0.0), (-3.0, 0.0, 0.0), (1.0, 0.0, 0.0)),
+    ];
+    for (from, to, up) in cases {
+        let frame = CoordinateFrame::look_at(from, to, up);
+        let d = [to.0 - from.0, to.1 - from.1, to.2 - from.2];
+        let len = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+
+        // the frame looks down its -Z axis
+        let look = frame.mul(&[0.0, 0.0, -1.0], false);
+        assert_close(look, d.map(|v| v / len));
+        assert_close(position(&frame), [from.0, from.1, from.2]);
+        assert!(frame.is_rigid(), "looking from {from:?} to {to:?}");
+    }
+}
+
+#[test]
+fn transforms_vector3_points() {
+    let v = Vector3::new(1.0, 2.0, 3.0);
+    let components = |v: Vector3| [v.x, v.y, v.z];
+
+    assert_close(components(CoordinateFrame::identity() * v), [1.0, 2.0, 3.0]);
+    assert_close(
+        components(CoordinateFrame::new(10.0, -1.0, 0.5) * v),
+        [11.0, 1.0, 3.5],
+    );
+    assert_close(
+        components(CoordinateFrame::rz(PI / 2.0) * v),
+        [-2.0, 1.0, 3.0],
+    );
+    assert_close(
+        components(CoordinateFrame::new(10.0, 0.0, 0.0) * CoordinateFrame::rz(PI / 2.0) * v),
+        [8.0, 1.0, 3.0],
+    );
+}
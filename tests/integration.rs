@@ -0,0 +1,51 @@
+use std::fs::File;
+
+use brickadia::read::SaveReader;
+use rbx_dom_weak::types::{CFrame, Matrix3, Variant, Vector3};
+
+#[test]
+fn converts_simple_save() {
+    let save = SaveReader::new(File::open("tests/fixtures/simple.brs").unwrap())
+        .and_then(|mut reader| reader.read_all_skip_preview())
+        .unwrap();
+    let dom = brs2rbxl::convert_save(&save);
+
+    let parts: Vec<_> = dom
+        .descendants()
+        .filter(|instance| instance.class == "Part")
+        .collect();
+    assert_eq!(parts.len(), 1);
+    let part = parts[0];
+
+    assert_eq!(
+        part.properties.get("Size"),
+        Some(&Variant::Vector3(Vector3::new(2.0, 1.2, 2.0)))
+    );
+    assert_eq!(
+        part.properties.get("CFrame"),
+        Some(&Variant::CFrame(CFrame::new(
+            Vector3::new(0.0, 0.6, 0.0),
+            Matrix3::new(
+                Vector3::new(-1.0, 0.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(0.0, 0.0, -1.0),
+            ),
+        )))
+    );
+    // colors go through an sRGB conversion, so are only exact to within float error
+    let Some(Variant::Color3(color)) = part.properties.get("Color") else {
+        panic!("part has no Color");
+    };
+    for (actual, expected) in [(color.r, 1.0), (color.g, 0.0), (color.b, 0.0)] {
+        assert!((actual - expected).abs() < 1e-4, "{actual} != {expected}");
+    }
+}
+
+#[test]
+fn every_known_asset_converts() {
+    let mapping = brs2rbxl::asset_mapping();
+    assert_eq!(mapping.len(), brs2rbxl::ASSETS.len());
+    for (asset, classes) in mapping {
+        assert!(!classes.is_empty(), "{asset} converted to nothing");
+    }
+}
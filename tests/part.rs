@@ -0,0 +1,89 @@
+use std::f32::consts::PI;
+
+use brickadia::save::{Brick, Color, SaveData};
+use brs2rbxl::{ConvertOptions, CoordinateFrame, PartDef};
+use rbx_dom_weak::{types::Variant, WeakDom};
+
+/// Where a part ends up for a brick at the origin, facing up, and its size.
+fn frame(part: PartDef) -> ([f32; 3], [f32; 3]) {
+    let mut save = SaveData::default();
+    save.header2.colors = vec![Color {
+        r: 255,
+        g: 255,
+        b: 255,
+        a: 255,
+    }];
+    save.header2.materials = vec!["BMC_Plastic".into()];
+    let instance = part.to_instance(&save, &Brick::default(), &ConvertOptions::default());
+    let dom = WeakDom::new(instance);
+    let properties = &dom.root().properties;
+    let (Some(Variant::CFrame(cframe)), Some(Variant::Vector3(size))) =
+        (properties.get("CFrame"), properties.get("Size"))
+    else {
+        panic!("part has no CFrame or Size");
+    };
+    (
+        [cframe.position.x, cframe.position.y, cframe.position.z],
+        [size.x, size.y, size.z],
+    )
+}
+
+#[test]
+fn offset_follows_rotation() {
+    let rotated = || PartDef::new("Part").cf(CoordinateFrame::ry(PI / 2.0));
+    let (local, _) = frame(rotated().offset(1.0, 0.0, 0.0));
+    let (world, _) = frame(rotated().world_offset(1.0, 0.0, 0.0));
+
+    // the default brick orientation flips X, so a world offset along X ends up at -1
+    assert!(
+        (world[0] + 1.0).abs() < 1e-5 && world[2].abs() < 1e-5,
+        "{world:?}"
+    );
+    // turned a quarter about Y, the part's own X points along Z
+    assert!(
+        local[0].abs() < 1e-5 && (local[2].abs() - 1.0).abs() < 1e-5,
+        "{local:?}"
+    );
+}
+
+#[test]
+fn merges_colinear_boxes() {
+    let a = PartDef::new("Part").size(1.0, 1.0, 1.0);
+    let b = PartDef::new("Part")
+        .size(2.0, 1.0, 1.0)
+        .offset(1.5, 0.0, 0.0);
+    let Ok(merged) = PartDef::try_merge(a, b) else {
+        panic!("boxes weren't merged");
+    };
+
+    let (position, size) = frame(merged);
+    // the brick's default orientation flips X
+    assert!((position[0] + 1.0).abs() < 1e-5, "{position:?}");
+    assert_eq!(size, [3.0, 1.0, 1.0]);
+
+    let a = PartDef::new("Part").size(1.0, 1.0, 1.0);
+    let c = PartDef::new("Part")
+        .size(1.0, 2.0, 1.0)
+        .offset(1.0, 0.0, 0.0);
+    assert!(PartDef::try_merge(a, c).is_err());
+}
+
+#[test]
+fn scale_follows_rotation() {
+    let part = PartDef::new("WedgePart")
+        .size(1.0, 2.0, 3.0)
+        .offset(1.0, 0.0, 0.0)
+        .cf(CoordinateFrame::ry(PI / 2.0))
+        .scale(2.0, 1.0, 1.0);
+    let (position, size) = frame(part);
+
+    // the offset is along the brick's X, flipped by its default orientation, and the part's own Z
+    // lies along the brick's X after the turn
+    assert!((position[0] + 2.0).abs() < 1e-5, "{position:?}");
+    assert!(
+        (size[0] - 1.0).abs() < 1e-5
+            && (size[1] - 2.0).abs() < 1e-5
+            && (size[2] - 6.0).abs() < 1e-5,
+        "{size:?}"
+    );
+}